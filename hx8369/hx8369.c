@@ -25,25 +25,6 @@
 #include "hx8369.h"
 static const char *TAG = "lcd_panel.hx8369";
 
-#define PIN_NUM_DATA0 46
-#define PIN_NUM_DATA1 3
-#define PIN_NUM_DATA2 8
-#define PIN_NUM_DATA3 18
-#define PIN_NUM_DATA4 17
-#define PIN_NUM_DATA5 16
-#define PIN_NUM_DATA6 15
-#define PIN_NUM_DATA7 7
-
-#define PIN_NUM_PCLK 10
-#define PIN_NUM_CS 12
-#define PIN_NUM_DC 11
-#define PIN_NUM_RST 9
-#define PIN_NUM_BK_LIGHT 6
-
-// Bit number used to represent command and parameter
-#define LCD_CMD_BITS 8
-#define LCD_PARAM_BITS 8
-
 static esp_err_t panel_hx8369_del(esp_lcd_panel_t *panel);
 static esp_err_t panel_hx8369_reset(esp_lcd_panel_t *panel);
 static esp_err_t panel_hx8369_init(esp_lcd_panel_t *panel);
@@ -184,79 +165,9 @@ static esp_err_t panel_hx8369_reset(esp_lcd_panel_t *panel) {
 static esp_err_t panel_hx8369_init(esp_lcd_panel_t *panel) {
   hx8369_panel_t *hx8369 = __containerof(panel, hx8369_panel_t, base);
   esp_lcd_panel_io_handle_t io = hx8369->io;
-  // Init LCD
-
-  // Set_EXTC
-  esp_lcd_panel_io_tx_param(io, 0xB9, (uint8_t[]){0xFF, 0x83, 0x69}, 3);
-  // Set Power
-  esp_lcd_panel_io_tx_param(
-      io, 0xB1,
-      (uint8_t[]){0x01, 0x00, 0x34, 0x06, 0x00, 0x0f, 0x0f, 0x2a, 0x32, 0x3f, //
-                  0x3f, 0x07, 0x23, 0x01, 0xe6, 0xe6, 0xe6, 0xe6, 0xe6},
-      19);
-
-  // SET Display 480x800
-  // 0x2b;0x20-MCU;0x29-DPI;RM,DM; RM=0:DPI IF;  RM=1:RGB IF;
-  esp_lcd_panel_io_tx_param(io, 0xB2,
-                            (uint8_t[]){0x00, 0x20, 0x03, 0x03, 0x70, 0x00,
-                                        0xff, 0x00, 0x00, 0x00, //
-                                        0x00, 0x03, 0x03, 0x00, 0x01},
-                            15);
-  // SET Display CYC
-  esp_lcd_panel_io_tx_param(io, 0xb4, (uint8_t[]){0x00, 0x0C, 0xA0, 0x0E, 0x06},
-                            5);
-  // SET VCOM
-  esp_lcd_panel_io_tx_param(io, 0xb6, (uint8_t[]){0x2C, 0x2C}, 2);
-
-  // SET GIP
-  esp_lcd_panel_io_tx_param(
-      io, 0xD5, (uint8_t[]){0x00, 0x05, 0x03, 0x00, 0x01, 0x09, 0x10,
-                            0x80, 0x37, 0x37, 0x20, 0x31, 0x46, //
-                            0x8a, 0x57, 0x9b, 0x20, 0x31, 0x46, 0x8a,
-                            0x57, 0x9b, 0x07, 0x0f, 0x02, 0x00},
-      26);
-
-  //  SET GAMMA
-  esp_lcd_panel_io_tx_param(
-      io, 0xE0,
-      (uint8_t[]){0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09,
-                  0x0e, 0x0e, 0x12, 0x14, 0x12, 0x14, 0x13, 0x19, //
-                  0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09,
-                  0x0e, 0x0e, 0x12, 0x14, 0x12, 0x14, 0x13, 0x19},
-      34);
-
-  // set DGC
-  esp_lcd_panel_io_tx_param(
-      io, 0xC1, (uint8_t[]){0x01, 0x02, 0x08, 0x12, 0x1a, 0x22, 0x2a, 0x31,
-                            0x36, 0x3f, 0x48, 0x51, 0x58, 0x60, 0x68, 0x70, //
-                            0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, 0xa7, 0xaf,
-                            0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde, 0xe6, 0xef, //
-                            0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, 0x19, 0xec,
-                            0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12, 0x1a, 0x22, //
-                            0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, 0x58, 0x60,
-                            0x68, 0x70, 0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, //
-                            0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde,
-                            0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, //
-                            0x19, 0xec, 0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12,
-                            0x1a, 0x22, 0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, //
-                            0x58, 0x60, 0x68, 0x70, 0x78, 0x80, 0x88, 0x90,
-                            0x98, 0xa0, 0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, //
-                            0xd6, 0xde, 0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe,
-                            0x8c, 0xa4, 0x19, 0xec, 0x1b, 0x4c, 0x40},
-      127);
-
-  //  Colour Set
-  uint8_t cmd_192[192];
-  for (size_t i = 0; i <= 63; i++) {
-    cmd_192[i] = i * 8;
-  }
-  for (size_t i = 64; i <= 127; i++) {
-    cmd_192[i] = i * 4;
-  }
-  for (size_t i = 128; i <= 191; i++) {
-    cmd_192[i] = i * 8;
-  }
-  esp_lcd_panel_io_tx_param(io, 0x2D, cmd_192, 192);
+  // The vendor specific init sequence (power, GIP, gamma, DGC, colour LUT) is
+  // sent from Rust between `hx8369_new_panel` and `hx8369_start`, only the
+  // generic part is left here
 
   // LCD goes into sleep mode and display will be turned off after power on
   // reset, exit sleep mode first
@@ -392,35 +303,31 @@ static bool notify_flush_ready(esp_lcd_panel_io_handle_t panel_io,
   return false;
 }
 
-esp_lcd_panel_handle_t hx8369_init(void) {
+esp_lcd_panel_io_handle_t hx8369_new_io(const hx8369_config_t *config) {
+  assert(config && config->bus_width <= HX8369_MAX_BUS_WIDTH &&
+         "invalid hx8369 config");
 
-  ESP_LOGI(TAG, "Initialize Intel 8080 bus");
+  ESP_LOGI(TAG, "Initialize Intel 8080 bus (%dx%d, %u bits, %lu Hz)",
+           config->h_res, config->v_res, (unsigned)config->bus_width,
+           (unsigned long)config->pixel_clock_hz);
   esp_lcd_i80_bus_handle_t i80_bus = NULL;
   esp_lcd_i80_bus_config_t bus_config = {
       .clk_src = LCD_CLK_SRC_PLL160M,
-      .dc_gpio_num = PIN_NUM_DC,
-      .wr_gpio_num = PIN_NUM_PCLK,
-      .data_gpio_nums =
-          {
-              PIN_NUM_DATA0,
-              PIN_NUM_DATA1,
-              PIN_NUM_DATA2,
-              PIN_NUM_DATA3,
-              PIN_NUM_DATA4,
-              PIN_NUM_DATA5,
-              PIN_NUM_DATA6,
-              PIN_NUM_DATA7,
-          },
-      .bus_width = 8,
-      .max_transfer_bytes = LCD_V_RES * 100 * sizeof(uint16_t),
+      .dc_gpio_num = config->dc_gpio_num,
+      .wr_gpio_num = config->pclk_gpio_num,
+      .bus_width = config->bus_width,
+      .max_transfer_bytes = config->max_transfer_bytes,
       .psram_trans_align = PSRAM_DATA_ALIGNMENT,
       .sram_trans_align = 4,
   };
+  for (size_t i = 0; i < config->bus_width; i++) {
+    bus_config.data_gpio_nums[i] = config->data_gpio_nums[i];
+  }
   ESP_ERROR_CHECK(esp_lcd_new_i80_bus(&bus_config, &i80_bus));
   esp_lcd_panel_io_handle_t io_handle = NULL;
   esp_lcd_panel_io_i80_config_t io_config = {
-      .cs_gpio_num = PIN_NUM_CS,
-      .pclk_hz = LCD_PIXEL_CLOCK_HZ,
+      .cs_gpio_num = config->cs_gpio_num,
+      .pclk_hz = config->pixel_clock_hz,
       .trans_queue_depth = 10,
       .dc_levels =
           {
@@ -438,34 +345,46 @@ esp_lcd_panel_handle_t hx8369_init(void) {
   };
   ESP_ERROR_CHECK(esp_lcd_new_panel_io_i80(i80_bus, &io_config, &io_handle));
 
+  return io_handle;
+}
+
+esp_lcd_panel_handle_t hx8369_new_panel(esp_lcd_panel_io_handle_t io_handle,
+                                        const hx8369_config_t *config) {
   esp_lcd_panel_handle_t panel_handle = NULL;
   panel_handle = NULL;
 
   ESP_LOGI(TAG, "Install LCD driver of hx8369");
   esp_lcd_panel_dev_config_t panel_config = {
-      .reset_gpio_num = PIN_NUM_RST,
-      .color_space = ESP_LCD_COLOR_SPACE_RGB,
+      .reset_gpio_num = config->rst_gpio_num,
+      .color_space = config->bgr_order ? ESP_LCD_COLOR_SPACE_BGR
+                                       : ESP_LCD_COLOR_SPACE_RGB,
       .bits_per_pixel = 16,
   };
   ESP_ERROR_CHECK(
       esp_lcd_new_panel_hx8369(io_handle, &panel_config, &panel_handle));
 
   esp_lcd_panel_reset(panel_handle);
+
+  return panel_handle;
+}
+
+void hx8369_start(esp_lcd_panel_handle_t panel_handle,
+                  const hx8369_config_t *config) {
   esp_lcd_panel_init(panel_handle);
 
-  // Set inversion, x/y coordinate order, x/y mirror according to your LCD
-  // module spec esp_lcd_panel_invert_color(panel_handle, false);
-  esp_lcd_panel_swap_xy(panel_handle, true);
-  esp_lcd_panel_mirror(panel_handle, true, false);
+  // Set inversion, x/y coordinate order, x/y mirror according to the LCD
+  // module spec passed in the config
+  esp_lcd_panel_invert_color(panel_handle, config->invert_color);
+  esp_lcd_panel_swap_xy(panel_handle, config->swap_xy);
+  esp_lcd_panel_mirror(panel_handle, config->mirror_x, config->mirror_y);
 
   // the gap is LCD panel specific, even panels with the same driver IC, can
-  // have different gap value esp_lcd_panel_set_gap(panel_handle, 0, 20);
+  // have different gap value
+  esp_lcd_panel_set_gap(panel_handle, config->x_gap, config->y_gap);
 
   // user can flush pre-defined pattern to the screen before we turn on the
   // screen or backlight
   // ESP_ERROR_CHECK(esp_lcd_panel_disp_on_off(panel_handle,
   // true));panel_hx8369_disp_on_off
   ESP_ERROR_CHECK(panel_hx8369_disp_on_off(panel_handle, true));
-
-  return panel_handle;
 }
@@ -9,45 +9,64 @@ extern "C" {
  *      INCLUDES
  *********************/
 #include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
 
 #include "esp_lcd_panel_interface.h"
 #include "esp_lcd_types.h"
 
 #include "sdkconfig.h"
 
-#define LCD_H_RES 800
-#define LCD_V_RES 480
 /*********************
  *      DEFINES
  *********************/
-#define PIN_NUM_DATA0 46
-#define PIN_NUM_DATA1 3
-#define PIN_NUM_DATA2 8
-#define PIN_NUM_DATA3 18
-#define PIN_NUM_DATA4 17
-#define PIN_NUM_DATA5 16
-#define PIN_NUM_DATA6 15
-#define PIN_NUM_DATA7 7
-
-#define PIN_NUM_PCLK 10
-#define PIN_NUM_CS 12
-#define PIN_NUM_DC 11
-#define PIN_NUM_RST 9
-#define PIN_NUM_BK_LIGHT 6
-
 // Bit number used to represent command and parameter
 #define LCD_CMD_BITS 8
 #define LCD_PARAM_BITS 8
 
-#define LCD_PIXEL_CLOCK_HZ (20 * 1000 * 1000)
 // Supported alignment: 16, 32, 64. A higher alignment can enables higher burst transfer size, thus a higher i80 bus throughput.
 #define PSRAM_DATA_ALIGNMENT 64
 
+// The i80 bus on ESP32-S3 is at most 16 bits wide
+#define HX8369_MAX_BUS_WIDTH 16
+
+/**********************
+ *      TYPEDEFS
+ **********************/
+// Board specific parameters, filled by the caller and passed to `hx8369_new_io`,
+// `hx8369_new_panel` and `hx8369_start`
+typedef struct {
+  int data_gpio_nums[HX8369_MAX_BUS_WIDTH];
+  size_t bus_width;
+  int pclk_gpio_num;
+  int cs_gpio_num;
+  int dc_gpio_num;
+  int rst_gpio_num;
+  int h_res;
+  int v_res;
+  uint32_t pixel_clock_hz;
+  size_t max_transfer_bytes;
+  int x_gap;
+  int y_gap;
+  bool bgr_order;
+  bool invert_color;
+  bool swap_xy;
+  bool mirror_x;
+  bool mirror_y;
+} hx8369_config_t;
+
 /**********************
  * GLOBAL PROTOTYPES
  **********************/
 extern      esp_lcd_panel_handle_t panel_handle;
-esp_lcd_panel_handle_t    hx8369_init(void);
+// Create the i80 bus and the panel IO, the vendor init sequence is sent on it
+esp_lcd_panel_io_handle_t hx8369_new_io(const hx8369_config_t *config);
+// Create the panel and reset it
+esp_lcd_panel_handle_t    hx8369_new_panel(esp_lcd_panel_io_handle_t io,
+                                           const hx8369_config_t *config);
+// Exit sleep, set pixel format and orientation, then turn on the display
+void                      hx8369_start(esp_lcd_panel_handle_t panel,
+                                       const hx8369_config_t *config);
 
 #ifdef __cplusplus
 } /* extern "C" */
@@ -0,0 +1,149 @@
+/// Attract mode, the solvers take turns on a maze while nobody is playing
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor},
+    Drawable,
+};
+use log::info;
+
+use crate::{
+    maze_painter::{MazePainter, SearchStyle},
+    search::{Search, SearchAlgorithm, SearchEvent},
+};
+
+// How long the found path stays on the screen before the next solver starts
+const PAUSE: Duration = Duration::from_secs(3);
+// Missed steps run at most this many at a time, the rest are skipped
+const MAX_CATCH_UP: usize = 8;
+
+pub struct Attract<C: PixelColor> {
+    painter: MazePainter<C>,
+    style: SearchStyle<C>,
+    background: C,
+    search: Search,
+    step_delay: Duration,
+    next_step: Instant,
+}
+
+impl<C: PixelColor> Attract<C> {
+    /// Start with the first solver on the maze of the painter, nothing is drawn yet
+    pub fn new(
+        painter: MazePainter<C>,
+        style: SearchStyle<C>,
+        background: C,
+        step_delay: Duration,
+        now: Instant,
+    ) -> Self {
+        let search = Search::new(
+            &painter.maze,
+            SearchAlgorithm::ALL[0],
+            painter.start,
+            painter.goal,
+        );
+        Self {
+            painter,
+            style,
+            background,
+            search,
+            step_delay,
+            next_step: now,
+        }
+    }
+
+    /// Clear the screen and draw the maze
+    pub fn draw<D>(&self, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        target.clear(self.background).ok();
+        self.painter.draw(target).ok();
+    }
+
+    /// Run the steps that are due, returns `true` if anything was drawn
+    pub fn update<D>(&mut self, now: Instant, target: &mut D) -> bool
+    where
+        D: DrawTarget<Color = C>,
+    {
+        if now < self.next_step {
+            return false;
+        }
+        if self.search.is_done() {
+            let next = SearchAlgorithm::ALL
+                .iter()
+                .position(|a| *a == self.search.algorithm())
+                .map_or(0, |i| (i + 1) % SearchAlgorithm::ALL.len());
+            info!("Attract mode with {:?}", SearchAlgorithm::ALL[next]);
+            self.search = Search::new(
+                &self.painter.maze,
+                SearchAlgorithm::ALL[next],
+                self.painter.start,
+                self.painter.goal,
+            );
+            self.draw(target);
+            self.next_step = now + self.step_delay;
+            return true;
+        }
+        // Catch up with the missed steps, but don't stall the main loop for long
+        let mut drawn = false;
+        for _ in 0..MAX_CATCH_UP {
+            if self.next_step > now {
+                break;
+            }
+            let Some(event) = self.search.step(&self.painter.maze) else {
+                break;
+            };
+            self.painter.draw_search_event(&event, &self.style, target);
+            drawn = true;
+            if let SearchEvent::Done(_) = event {
+                self.next_step = now + PAUSE;
+                break;
+            }
+            self.next_step += self.step_delay;
+        }
+        self.next_step = self.next_step.max(now);
+        drawn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::{Point, Size},
+        primitives::PrimitiveStyle,
+    };
+
+    use super::*;
+    use crate::{framebuffer::FrameBuffer, maze::Maze};
+
+    #[test]
+    fn catching_up_is_limited() {
+        let mut maze = Maze::new(20, 20);
+        maze.generate_seeded(0, 0, 1);
+        let painter = MazePainter::new(
+            maze,
+            PrimitiveStyle::with_stroke(Rgb565::WHITE, 1),
+            Size::new(10, 10),
+            Point::zero(),
+        );
+        let style = SearchStyle {
+            frontier: Rgb565::BLUE,
+            visited: Rgb565::CYAN,
+            backtracked: Rgb565::RED,
+            path: PrimitiveStyle::with_stroke(Rgb565::GREEN, 3),
+        };
+        let now = Instant::now();
+        let step = Duration::from_millis(10);
+        let mut attract = Attract::new(painter, style, Rgb565::BLACK, step, now);
+        let mut target = FrameBuffer::new(200, 200);
+        attract.draw(&mut target);
+        // Way behind, only a few of the missed steps are run and the rest are dropped
+        let later = now + Duration::from_secs(60);
+        assert!(attract.update(later, &mut target));
+        assert!(!attract.search.is_done());
+        assert_eq!(attract.next_step, later);
+        assert!(attract.update(later, &mut target));
+        assert!(!attract.update(later - step, &mut target));
+    }
+}
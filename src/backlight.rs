@@ -0,0 +1,85 @@
+/// Backlight driver on top of the LEDC PWM peripheral.
+/// The board wires the backlight to GPIO6, which also needs to be high before resetting the GT911.
+use std::time::{Duration, Instant};
+
+use esp_idf_svc::{hal::ledc::LedcDriver, sys::EspError};
+
+use crate::{
+    idle::{Fade, MAX_BRIGHTNESS},
+    power::Dimmer,
+};
+
+pub struct Backlight<'d> {
+    driver: LedcDriver<'d>,
+    level: u8,
+    fade: Option<Fade>,
+}
+
+impl<'d> Backlight<'d> {
+    /// Take over the PWM channel and turn the backlight on at full brightness
+    pub fn new(driver: LedcDriver<'d>) -> Result<Self, EspError> {
+        let mut backlight = Self {
+            driver,
+            level: 0,
+            fade: None,
+        };
+        backlight.set_brightness(MAX_BRIGHTNESS)?;
+        Ok(backlight)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Set brightness in percent immediately, cancels any running fade
+    pub fn set_brightness(&mut self, level: u8) -> Result<(), EspError> {
+        self.fade = None;
+        self.apply(level)
+    }
+
+    /// Start a transition to `level`, call `update` periodically to make progress
+    pub fn fade_to(&mut self, level: u8, duration: Duration, now: Instant) {
+        let level = level.min(MAX_BRIGHTNESS);
+        if level == self.level {
+            self.fade = None;
+            return;
+        }
+        self.fade = Some(Fade::new(self.level, level, now, duration));
+    }
+
+    pub fn update(&mut self, now: Instant) -> Result<(), EspError> {
+        if let Some(fade) = self.fade {
+            if fade.is_done(now) {
+                self.fade = None;
+            }
+            self.apply(fade.level_at(now))?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, level: u8) -> Result<(), EspError> {
+        let level = level.min(MAX_BRIGHTNESS);
+        let duty = self.driver.get_max_duty() * level as u32 / MAX_BRIGHTNESS as u32;
+        self.driver.set_duty(duty)?;
+        self.level = level;
+        Ok(())
+    }
+}
+
+impl Dimmer for Backlight<'_> {
+    fn level(&self) -> u8 {
+        Backlight::level(self)
+    }
+
+    fn set_brightness(&mut self, level: u8) -> anyhow::Result<()> {
+        Ok(Backlight::set_brightness(self, level)?)
+    }
+
+    fn fade_to(&mut self, level: u8, duration: Duration, now: Instant) {
+        Backlight::fade_to(self, level, duration, now)
+    }
+
+    fn update(&mut self, now: Instant) -> anyhow::Result<()> {
+        Ok(Backlight::update(self, now)?)
+    }
+}
@@ -0,0 +1,107 @@
+/// Block grid version of a maze, the walls take whole tiles like in most tile based games
+use std::ops::Index;
+
+use crate::maze::Maze;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Wall,
+    Floor,
+}
+
+/// `(2 * width + 1) x (2 * height + 1)` tiles for a maze of `width x height` cells
+pub struct BlockGrid {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Tile>,
+}
+
+impl BlockGrid {
+    /// Cell `(x, y)` becomes the tile `(2x + 1, 2y + 1)` and the passages between the cells
+    /// the tiles in between, everything else is a wall. Cells outside of the mask stay walls,
+    /// the crossings of a weave maze become plain junctions.
+    pub fn from_maze(maze: &Maze) -> Self {
+        let width = 2 * maze.width + 1;
+        let height = 2 * maze.height + 1;
+        let mut tiles = vec![Tile::Wall; width * height];
+        for y in 0..maze.height {
+            for x in 0..maze.width {
+                let cell = &maze[(x, y)];
+                if !cell.active {
+                    continue;
+                }
+                let (tx, ty) = Self::cell_tile(x, y);
+                tiles[ty * width + tx] = Tile::Floor;
+                if cell.east && x + 1 < maze.width {
+                    tiles[ty * width + tx + 1] = Tile::Floor;
+                }
+                if cell.south && y + 1 < maze.height {
+                    tiles[(ty + 1) * width + tx] = Tile::Floor;
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    pub fn cell_tile(x: usize, y: usize) -> (usize, usize) {
+        (2 * x + 1, 2 * y + 1)
+    }
+
+    /// The cells touching the tile: one for a cell, two for the wall or passage between two
+    /// cells, up to four for a corner
+    pub fn cells_around(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let range = |t: usize, cells: usize| {
+            let first = t.saturating_sub(1) / 2;
+            let last = (t / 2).min(cells - 1);
+            first..=last
+        };
+        let (cells_x, cells_y) = (self.width / 2, self.height / 2);
+        range(y, cells_y)
+            .flat_map(|cy| range(x, cells_x).map(move |cx| (cx, cy)))
+            .collect()
+    }
+}
+
+impl Index<(usize, usize)> for BlockGrid {
+    type Output = Tile;
+
+    fn index(&self, index: (usize, usize)) -> &Self::Output {
+        &self.tiles[index.1 * self.width + index.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passages_become_floor_tiles() {
+        // Two cells side by side with a passage, a closed cell below the first one
+        let mut maze = Maze::new(2, 2);
+        maze.carve((0, 0), (1, 0));
+        let grid = BlockGrid::from_maze(&maze);
+        assert_eq!((grid.width, grid.height), (5, 5));
+        assert_eq!(grid[(1, 1)], Tile::Floor);
+        assert_eq!(grid[(2, 1)], Tile::Floor);
+        assert_eq!(grid[(3, 1)], Tile::Floor);
+        assert_eq!(grid[(1, 2)], Tile::Wall);
+        assert_eq!(grid[(0, 1)], Tile::Wall);
+    }
+
+    #[test]
+    fn cells_around_tiles() {
+        let grid = BlockGrid::from_maze(&Maze::new(3, 2));
+        assert_eq!(grid.cells_around(3, 1), vec![(1, 0)]);
+        assert_eq!(grid.cells_around(2, 1), vec![(0, 0), (1, 0)]);
+        assert_eq!(
+            grid.cells_around(2, 2),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+        assert_eq!(grid.cells_around(0, 0), vec![(0, 0)]);
+        assert_eq!(grid.cells_around(6, 4), vec![(2, 1)]);
+    }
+}
@@ -0,0 +1,356 @@
+/// Timed game modes on top of `Maze`, the timing only depends on a `Clock`
+use std::{collections::HashMap, time::Duration};
+
+use crate::clock::Clock;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChallengeMode {
+    /// Solve a single maze before the time runs out
+    Countdown {
+        width: usize,
+        height: usize,
+        limit: Duration,
+    },
+    /// A sequence of increasingly larger mazes, the time left is carried over to the next one
+    Survival {
+        width: usize,
+        height: usize,
+        growth: usize,
+        max_width: usize,
+        max_height: usize,
+        initial_time: Duration,
+        bonus_per_level: Duration,
+    },
+    /// No time limit, the time to solve the maze goes to the leaderboard of the seed
+    TimeAttack { width: usize, height: usize },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChallengeState {
+    Running,
+    Solved,
+    TimedOut,
+}
+
+pub struct Challenge<K: Clock> {
+    mode: ChallengeMode,
+    clock: K,
+    seed: u64,
+    level: usize,
+    level_start: Duration,
+    // Time available for the current maze, `None` if there is no limit
+    budget: Option<Duration>,
+    // Time used when the maze was solved or timed out
+    finished_in: Option<Duration>,
+    // Clock time when the game was paused, `None` while running
+    paused_at: Option<Duration>,
+    state: ChallengeState,
+}
+
+impl<K: Clock> Challenge<K> {
+    /// Start the first maze, the clock starts running right away
+    pub fn new(mode: ChallengeMode, seed: u64, clock: K) -> Self {
+        let budget = match mode {
+            ChallengeMode::Countdown { limit, .. } => Some(limit),
+            ChallengeMode::Survival { initial_time, .. } => Some(initial_time),
+            ChallengeMode::TimeAttack { .. } => None,
+        };
+        let level_start = clock.now();
+        Self {
+            mode,
+            clock,
+            seed,
+            level: 0,
+            level_start,
+            budget,
+            finished_in: None,
+            paused_at: None,
+            state: ChallengeState::Running,
+        }
+    }
+
+    pub fn mode(&self) -> ChallengeMode {
+        self.mode
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Seed of the current maze, every level of a survival run gets its own maze
+    pub fn seed(&self) -> u64 {
+        self.seed.wrapping_add(self.level as u64)
+    }
+
+    /// Size of the current maze
+    pub fn maze_size(&self) -> (usize, usize) {
+        match self.mode {
+            ChallengeMode::Countdown { width, height, .. }
+            | ChallengeMode::TimeAttack { width, height } => (width, height),
+            ChallengeMode::Survival {
+                width,
+                height,
+                growth,
+                max_width,
+                max_height,
+                ..
+            } => (
+                (width + growth * self.level).min(max_width),
+                (height + growth * self.level).min(max_height),
+            ),
+        }
+    }
+
+    /// Stop the clock while the maze is not played, e.g. during the attract mode or in the settings
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(self.clock.now());
+        }
+    }
+
+    /// Start the clock again, the time spent paused doesn't count
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.level_start += self.clock.now().saturating_sub(paused_at);
+        }
+    }
+
+    /// Clock time, stopped while paused
+    fn now(&self) -> Duration {
+        self.paused_at.unwrap_or_else(|| self.clock.now())
+    }
+
+    /// Time spent on the current maze, stops when the maze is solved or the time is up
+    pub fn elapsed(&self) -> Duration {
+        self.finished_in
+            .unwrap_or_else(|| self.now().saturating_sub(self.level_start))
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.elapsed()))
+    }
+
+    /// Check the clock, returns the new state
+    pub fn tick(&mut self) -> ChallengeState {
+        if self.state == ChallengeState::Running && self.remaining() == Some(Duration::ZERO) {
+            self.finished_in = Some(self.elapsed());
+            self.state = ChallengeState::TimedOut;
+        }
+        self.state
+    }
+
+    /// The player reached the goal, returns the time used if it happened in time
+    pub fn solve(&mut self) -> Option<Duration> {
+        if self.tick() != ChallengeState::Running {
+            return None;
+        }
+        let elapsed = self.elapsed();
+        self.finished_in = Some(elapsed);
+        self.state = ChallengeState::Solved;
+        Some(elapsed)
+    }
+
+    /// Move on to the next maze of a survival run, the time left plus the bonus is the budget
+    /// for the next maze. Returns `false` if there is no next maze.
+    pub fn next_level(&mut self) -> bool {
+        let ChallengeMode::Survival {
+            bonus_per_level, ..
+        } = self.mode
+        else {
+            return false;
+        };
+        if self.state != ChallengeState::Solved {
+            return false;
+        }
+        self.budget = self
+            .remaining()
+            .map(|remaining| remaining + bonus_per_level);
+        self.level += 1;
+        self.level_start = self.now();
+        self.finished_in = None;
+        self.state = ChallengeState::Running;
+        true
+    }
+}
+
+/// Best times of time attack runs, keyed by the seed of the maze
+pub struct Leaderboard {
+    size: usize,
+    entries: HashMap<u64, Vec<Duration>>,
+}
+
+impl Leaderboard {
+    /// Keep the `size` best times for each seed
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Record a time, returns the rank (0 is the best) if it made it to the board
+    pub fn record(&mut self, seed: u64, time: Duration) -> Option<usize> {
+        let times = self.entries.entry(seed).or_default();
+        let rank = times.partition_point(|t| *t <= time);
+        if rank >= self.size {
+            return None;
+        }
+        times.insert(rank, time);
+        times.truncate(self.size);
+        Some(rank)
+    }
+
+    pub fn times(&self, seed: u64) -> &[Duration] {
+        self.entries.get(&seed).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn survival() -> ChallengeMode {
+        ChallengeMode::Survival {
+            width: 10,
+            height: 6,
+            growth: 4,
+            max_width: 20,
+            max_height: 12,
+            initial_time: 60 * SECOND,
+            bonus_per_level: 10 * SECOND,
+        }
+    }
+
+    #[test]
+    fn countdown_times_out() {
+        let clock = ManualClock::new();
+        let mode = ChallengeMode::Countdown {
+            width: 10,
+            height: 6,
+            limit: 30 * SECOND,
+        };
+        let mut challenge = Challenge::new(mode, 1, &clock);
+        clock.advance(29 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::Running);
+        assert_eq!(challenge.remaining(), Some(SECOND));
+        clock.advance(SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::TimedOut);
+        assert_eq!(challenge.solve(), None);
+        // The time stops with the challenge
+        clock.advance(10 * SECOND);
+        assert_eq!(challenge.elapsed(), 30 * SECOND);
+        assert!(!challenge.next_level());
+    }
+
+    #[test]
+    fn countdown_solved_in_time() {
+        let clock = ManualClock::new();
+        let mode = ChallengeMode::Countdown {
+            width: 10,
+            height: 6,
+            limit: 30 * SECOND,
+        };
+        let mut challenge = Challenge::new(mode, 1, &clock);
+        clock.advance(12 * SECOND);
+        assert_eq!(challenge.solve(), Some(12 * SECOND));
+        clock.advance(30 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::Solved);
+    }
+
+    #[test]
+    fn survival_carries_the_time_over_and_grows() {
+        let clock = ManualClock::new();
+        let mut challenge = Challenge::new(survival(), 7, &clock);
+        assert_eq!(challenge.maze_size(), (10, 6));
+        assert!(!challenge.next_level());
+        clock.advance(20 * SECOND);
+        assert_eq!(challenge.solve(), Some(20 * SECOND));
+        assert!(challenge.next_level());
+        assert_eq!(challenge.level(), 1);
+        assert_eq!(challenge.seed(), 8);
+        assert_eq!(challenge.maze_size(), (14, 10));
+        // 40s left plus the bonus
+        assert_eq!(challenge.remaining(), Some(50 * SECOND));
+        clock.advance(10 * SECOND);
+        challenge.solve();
+        challenge.next_level();
+        assert_eq!(challenge.maze_size(), (18, 12));
+        challenge.solve();
+        challenge.next_level();
+        assert_eq!(challenge.maze_size(), (20, 12));
+        clock.advance(50 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::Running);
+        clock.advance(20 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::TimedOut);
+    }
+
+    #[test]
+    fn time_attack_has_no_limit() {
+        let clock = ManualClock::new();
+        let mode = ChallengeMode::TimeAttack {
+            width: 10,
+            height: 6,
+        };
+        let mut challenge = Challenge::new(mode, 3, &clock);
+        assert_eq!(challenge.remaining(), None);
+        clock.advance(3600 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::Running);
+        assert_eq!(challenge.solve(), Some(3600 * SECOND));
+        assert!(!challenge.next_level());
+    }
+
+    #[test]
+    fn paused_time_does_not_count() {
+        let clock = ManualClock::new();
+        let mut challenge = Challenge::new(survival(), 7, &clock);
+        clock.advance(10 * SECOND);
+        challenge.pause();
+        clock.advance(120 * SECOND);
+        assert_eq!(challenge.tick(), ChallengeState::Running);
+        assert_eq!(challenge.elapsed(), 10 * SECOND);
+        // Pausing twice keeps the first pause
+        challenge.pause();
+        challenge.resume();
+        assert_eq!(challenge.remaining(), Some(50 * SECOND));
+        clock.advance(5 * SECOND);
+        assert_eq!(challenge.elapsed(), 15 * SECOND);
+        challenge.resume();
+        assert_eq!(challenge.elapsed(), 15 * SECOND);
+    }
+
+    #[test]
+    fn next_level_while_paused_starts_on_resume() {
+        let clock = ManualClock::new();
+        let mut challenge = Challenge::new(survival(), 7, &clock);
+        clock.advance(10 * SECOND);
+        challenge.solve();
+        challenge.pause();
+        challenge.next_level();
+        clock.advance(30 * SECOND);
+        challenge.resume();
+        assert_eq!(challenge.elapsed(), Duration::ZERO);
+        assert_eq!(challenge.remaining(), Some(60 * SECOND));
+    }
+
+    #[test]
+    fn leaderboard_ranks_the_best_times() {
+        let mut leaderboard = Leaderboard::new(3);
+        assert_eq!(leaderboard.record(1, 30 * SECOND), Some(0));
+        assert_eq!(leaderboard.record(1, 20 * SECOND), Some(0));
+        assert_eq!(leaderboard.record(1, 40 * SECOND), Some(2));
+        // A tie goes after the existing time
+        assert_eq!(leaderboard.record(1, 30 * SECOND), Some(2));
+        assert_eq!(
+            leaderboard.times(1),
+            &[20 * SECOND, 30 * SECOND, 30 * SECOND]
+        );
+        assert_eq!(leaderboard.record(1, 50 * SECOND), None);
+        // Every seed has its own board
+        assert_eq!(leaderboard.times(2), &[]);
+        assert_eq!(leaderboard.record(2, 50 * SECOND), Some(0));
+    }
+}
@@ -0,0 +1,65 @@
+/// Game clock abstraction, the game logic asks the clock for the time instead of calling
+/// `Instant::now()` directly, so it can be driven by a manual clock off the device.
+#[cfg(test)]
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    /// Time since the clock started
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, for the tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+impl<K: Clock> Clock for &K {
+    fn now(&self) -> Duration {
+        (*self).now()
+    }
+}
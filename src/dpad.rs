@@ -0,0 +1,99 @@
+/// On-screen direction pad, four buttons around a center point
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::{Primitive, PrimitiveStyle, Rectangle, Triangle},
+    Drawable,
+};
+
+use crate::maze::Direction;
+
+pub struct DPad<C: PixelColor> {
+    pub center: Point,
+    pub button_size: u32,
+    pub button_style: PrimitiveStyle<C>,
+    pub arrow_style: PrimitiveStyle<C>,
+}
+
+impl<C: PixelColor> DPad<C> {
+    pub fn new(
+        center: Point,
+        button_size: u32,
+        button_style: PrimitiveStyle<C>,
+        arrow_style: PrimitiveStyle<C>,
+    ) -> Self {
+        Self {
+            center,
+            button_size,
+            button_style,
+            arrow_style,
+        }
+    }
+
+    /// Area of the button, the buttons form a cross with an empty center
+    pub fn button_area(&self, direction: Direction) -> Rectangle {
+        let (dx, dy) = direction.offset();
+        let size = self.button_size as i32;
+        let center = self.center + Point::new(dx * size, dy * size);
+        Rectangle::with_center(center, Size::new(self.button_size, self.button_size))
+    }
+
+    pub fn hit(&self, point: Point) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|d| self.button_area(*d).contains(point))
+    }
+
+    fn arrow(&self, direction: Direction) -> Triangle {
+        let area = self.button_area(direction);
+        let center = area.center();
+        let r = self.button_size as i32 / 4;
+        let (dx, dy) = direction.offset();
+        // Tip points to the direction, the base is perpendicular to it
+        let tip = center + Point::new(dx * r, dy * r);
+        let base = center - Point::new(dx * r, dy * r);
+        let side = Point::new(dy * r, dx * r);
+        Triangle::new(tip, base + side, base - side)
+    }
+}
+
+impl<C: PixelColor> Drawable for DPad<C> {
+    type Color = C;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        for direction in Direction::ALL {
+            self.button_area(direction)
+                .into_styled(self.button_style)
+                .draw(target)?;
+            self.arrow(direction)
+                .into_styled(self.arrow_style)
+                .draw(target)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+
+    use super::*;
+
+    #[test]
+    fn buttons_around_the_center() {
+        let style = PrimitiveStyle::with_fill(Rgb565::WHITE);
+        let dpad = DPad::new(Point::new(100, 100), 40, style, style);
+        assert_eq!(dpad.hit(Point::new(100, 65)), Some(Direction::North));
+        assert_eq!(dpad.hit(Point::new(100, 135)), Some(Direction::South));
+        assert_eq!(dpad.hit(Point::new(135, 100)), Some(Direction::East));
+        assert_eq!(dpad.hit(Point::new(65, 100)), Some(Direction::West));
+        // The center and the corners are not buttons
+        assert_eq!(dpad.hit(Point::new(100, 100)), None);
+        assert_eq!(dpad.hit(Point::new(135, 135)), None);
+        assert_eq!(dpad.hit(Point::new(100, 170)), None);
+    }
+}
@@ -0,0 +1,365 @@
+/// Enemies walking through the maze on their own, the round is lost when one of them
+/// catches the player.
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Size},
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use log::info;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    maze::{Direction, Maze},
+    maze_painter::MazePainter,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Behavior {
+    /// Picks a random way at every junction, only turns back in dead ends
+    RandomWalker,
+    /// Keeps its left hand on the wall
+    WallFollower,
+    /// Takes the shortest path to the player
+    Chaser,
+}
+
+pub struct Enemy<C: PixelColor> {
+    pub behavior: Behavior,
+    position: (usize, usize),
+    heading: Direction,
+    style: PrimitiveStyle<C>,
+    // Time between two moves
+    period: Duration,
+    next_move: Instant,
+    // Where the enemy was drawn last time, needs to be erased before drawing it again
+    drawn: Option<(usize, usize)>,
+}
+
+impl<C: PixelColor> Enemy<C> {
+    pub fn new(
+        behavior: Behavior,
+        position: (usize, usize),
+        period: Duration,
+        style: PrimitiveStyle<C>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            behavior,
+            position,
+            heading: Direction::North,
+            style,
+            period,
+            next_move: now + period,
+            drawn: None,
+        }
+    }
+
+    /// The cell the enemy goes to next, enemies don't have keys and respect one-way passages
+    fn next_cell<R: Rng>(
+        &mut self,
+        maze: &Maze,
+        player: (usize, usize),
+        rng: &mut R,
+    ) -> Option<(usize, usize)> {
+        let (x, y) = self.position;
+        let direction = match self.behavior {
+            Behavior::RandomWalker => {
+                let exits: Vec<Direction> = Direction::ALL
+                    .into_iter()
+                    .filter(|d| maze.can_move(x, y, *d, 0).is_some())
+                    .collect();
+                let forward: Vec<Direction> = exits
+                    .iter()
+                    .copied()
+                    .filter(|d| *d != self.heading.opposite())
+                    .collect();
+                if forward.is_empty() {
+                    exits.first().copied()
+                } else {
+                    forward.choose(rng).copied()
+                }
+            }
+            Behavior::WallFollower => [
+                self.heading.left(),
+                self.heading,
+                self.heading.right(),
+                self.heading.opposite(),
+            ]
+            .into_iter()
+            .find(|d| maze.can_move(x, y, *d, 0).is_some()),
+            Behavior::Chaser => first_step(maze, self.position, player),
+        }?;
+        self.heading = direction;
+        maze.can_move(x, y, direction, 0)
+    }
+
+    fn draw<D>(&mut self, painter: &MazePainter<C>, background: C, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        if let Some((x, y)) = self.drawn.take() {
+            cell_area(painter, x, y)
+                .into_styled(PrimitiveStyle::with_fill(background))
+                .draw(target)
+                .ok();
+            // Bring back the fill, the items and the path of the cell
+            painter.redraw_cell(x, y, target);
+        }
+        let (x, y) = self.position;
+        cell_area(painter, x, y)
+            .into_styled(self.style)
+            .draw(target)
+            .ok();
+        self.drawn = Some(self.position);
+    }
+}
+
+/// Inside of the cell, away from the walls so erasing it doesn't touch them
+fn cell_area<C: PixelColor>(painter: &MazePainter<C>, x: usize, y: usize) -> Rectangle {
+    let (cx, cy) = painter.get_cell_central_point(x, y);
+    let size = painter
+        .cell_size
+        .width
+        .min(painter.cell_size.height)
+        .saturating_sub(6);
+    Rectangle::with_center((cx, cy).into(), Size::new(size, size))
+}
+
+/// First move of the shortest path from `from` to `to`, `None` if already there or unreachable
+fn first_step(maze: &Maze, from: (usize, usize), to: (usize, usize)) -> Option<Direction> {
+    // Search backwards from the target, so the first step is where the search came from
+    let mut came_from: Vec<Option<Direction>> = vec![None; maze.width * maze.height];
+    let mut queue = VecDeque::new();
+    queue.push_back(to);
+    while let Some((x, y)) = queue.pop_front() {
+        if (x, y) == from {
+            return came_from[y * maze.width + x];
+        }
+        for direction in Direction::ALL {
+            let Some((nx, ny)) = maze.neighbor(x, y, direction) else {
+                continue;
+            };
+            // The enemy walks the other way, from (nx, ny) to (x, y)
+            if (nx, ny) == to
+                || came_from[ny * maze.width + nx].is_some()
+                || maze.can_move(nx, ny, direction.opposite(), 0).is_none()
+            {
+                continue;
+            }
+            came_from[ny * maze.width + nx] = Some(direction.opposite());
+            queue.push_back((nx, ny));
+        }
+    }
+    None
+}
+
+/// All the enemies of a round
+pub struct Enemies<C: PixelColor> {
+    pub enemies: Vec<Enemy<C>>,
+    background: C,
+    rng: StdRng,
+    // Where the player was at the last tick
+    player: Option<(usize, usize)>,
+}
+
+impl<C: PixelColor> Enemies<C> {
+    pub fn new(background: C, seed: u64) -> Self {
+        Self {
+            enemies: Vec::new(),
+            background,
+            rng: StdRng::seed_from_u64(seed),
+            player: None,
+        }
+    }
+
+    /// A random cell at least `min_distance` cells away from `player`, to spawn an enemy on
+    pub fn far_cell(
+        &mut self,
+        maze: &Maze,
+        player: (usize, usize),
+        min_distance: usize,
+    ) -> (usize, usize) {
+        let far: Vec<(usize, usize)> = (0..maze.height)
+            .flat_map(|y| (0..maze.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                maze[(x, y)].active
+                    && maze[(x, y)].crossing.is_none()
+                    && x.abs_diff(player.0) + y.abs_diff(player.1) >= min_distance
+            })
+            .collect();
+        match far.choose(&mut self.rng) {
+            Some(&cell) => cell,
+            None => (
+                self.rng.gen_range(0..maze.width),
+                self.rng.gen_range(0..maze.height),
+            ),
+        }
+    }
+
+    pub fn add(&mut self, enemy: Enemy<C>) {
+        info!("Spawning {:?} at {:?}", enemy.behavior, enemy.position);
+        self.enemies.push(enemy);
+    }
+
+    pub fn set_background(&mut self, background: C) {
+        self.background = background;
+    }
+
+    pub fn collides(&self, player: (usize, usize)) -> bool {
+        self.enemies.iter().any(|enemy| enemy.position == player)
+    }
+
+    /// Hold the moves for the time the game was paused, e.g. while the settings were shown
+    pub fn delay(&mut self, paused: Duration) {
+        for enemy in &mut self.enemies {
+            enemy.next_move += paused;
+        }
+    }
+
+    /// Move the enemies whose turn it is and redraw only the cells they left and entered.
+    /// Returns `true` if an enemy caught the player.
+    pub fn tick<D>(
+        &mut self,
+        painter: &MazePainter<C>,
+        player: (usize, usize),
+        now: Instant,
+        target: &mut D,
+    ) -> bool
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let previous = self.player.replace(player).unwrap_or(player);
+        let mut swapped = false;
+        for enemy in &mut self.enemies {
+            if now < enemy.next_move {
+                continue;
+            }
+            // Skip the missed moves instead of catching up all at once
+            enemy.next_move = (enemy.next_move + enemy.period).max(now);
+            if let Some(next) = enemy.next_cell(&painter.maze, player, &mut self.rng) {
+                // The enemy and the player walked past each other
+                swapped |= enemy.position == player && next == previous && player != previous;
+                enemy.position = next;
+                enemy.draw(painter, self.background, target);
+            }
+        }
+        swapped || self.collides(player)
+    }
+
+    /// Draw all the enemies, e.g. after the whole screen was redrawn
+    pub fn draw<D>(&mut self, painter: &MazePainter<C>, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        for enemy in &mut self.enemies {
+            enemy.drawn = None;
+            enemy.draw(painter, self.background, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::Point,
+    };
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    /// A corridor from west to east
+    fn corridor() -> MazePainter<Rgb565> {
+        let mut maze = Maze::new(6, 1);
+        for x in 1..6 {
+            maze.carve((x - 1, 0), (x, 0));
+        }
+        MazePainter::new(
+            maze,
+            PrimitiveStyle::with_stroke(Rgb565::WHITE, 1),
+            Size::new(10, 10),
+            Point::zero(),
+        )
+    }
+
+    fn enemies(behavior: Behavior, position: (usize, usize), now: Instant) -> Enemies<Rgb565> {
+        let mut enemies = Enemies::new(Rgb565::BLACK, 1);
+        enemies.add(Enemy::new(
+            behavior,
+            position,
+            Duration::from_millis(100),
+            PrimitiveStyle::with_fill(Rgb565::RED),
+            now,
+        ));
+        enemies
+    }
+
+    #[test]
+    fn catches_the_player_on_the_same_cell() {
+        let painter = corridor();
+        let mut target = FrameBuffer::new(64, 16);
+        let now = Instant::now();
+        let mut enemies = enemies(Behavior::Chaser, (5, 0), now);
+        let step = Duration::from_millis(100);
+        assert!(!enemies.tick(&painter, (1, 0), now + step, &mut target));
+        assert!(!enemies.tick(&painter, (1, 0), now + step * 2, &mut target));
+        assert!(!enemies.tick(&painter, (1, 0), now + step * 3, &mut target));
+        assert!(enemies.tick(&painter, (1, 0), now + step * 4, &mut target));
+    }
+
+    #[test]
+    fn catches_the_player_walking_past() {
+        let painter = corridor();
+        let mut target = FrameBuffer::new(64, 16);
+        let now = Instant::now();
+        // Heading north with the left hand on the wall, the enemy goes west first
+        let mut enemies = enemies(Behavior::WallFollower, (3, 0), now);
+        assert!(!enemies.tick(&painter, (2, 0), now, &mut target));
+        // Both move at the same time, into each other's cell
+        assert!(enemies.tick(
+            &painter,
+            (3, 0),
+            now + Duration::from_millis(100),
+            &mut target
+        ));
+    }
+
+    #[test]
+    fn moving_apart_is_not_a_catch() {
+        let painter = corridor();
+        let mut target = FrameBuffer::new(64, 16);
+        let now = Instant::now();
+        let mut enemies = enemies(Behavior::WallFollower, (3, 0), now);
+        assert!(!enemies.tick(&painter, (4, 0), now, &mut target));
+        assert!(!enemies.tick(
+            &painter,
+            (5, 0),
+            now + Duration::from_millis(100),
+            &mut target
+        ));
+    }
+
+    #[test]
+    fn delayed_moves_wait_for_the_pause() {
+        let painter = corridor();
+        let mut target = FrameBuffer::new(64, 16);
+        let now = Instant::now();
+        let mut enemies = enemies(Behavior::Chaser, (5, 0), now);
+        let step = Duration::from_millis(100);
+        enemies.delay(Duration::from_secs(5));
+        enemies.tick(&painter, (1, 0), now + step * 2, &mut target);
+        assert_eq!(enemies.enemies[0].position, (5, 0));
+        enemies.tick(
+            &painter,
+            (1, 0),
+            now + Duration::from_secs(5) + step,
+            &mut target,
+        );
+        assert_eq!(enemies.enemies[0].position, (4, 0));
+    }
+}
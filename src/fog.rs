@@ -0,0 +1,80 @@
+/// Fog-of-war, only the cells around the explored part of the maze are revealed
+use crate::maze::{Direction, Maze};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// Cells within the radius (in cells) of an explored cell, walls don't block the view
+    Radius(usize),
+    /// Cells that can be seen along straight corridors from an explored cell
+    LineOfSight,
+}
+
+pub struct Fog {
+    visibility: Visibility,
+    width: usize,
+    height: usize,
+    revealed: Vec<bool>,
+}
+
+impl Fog {
+    pub fn new(width: usize, height: usize, visibility: Visibility) -> Self {
+        Self {
+            visibility,
+            width,
+            height,
+            revealed: vec![false; width * height],
+        }
+    }
+
+    pub fn is_revealed(&self, x: usize, y: usize) -> bool {
+        self.revealed[y * self.width + x]
+    }
+
+    /// Reveal the cells visible from `(x, y)`, returns the cells that were not revealed before
+    pub fn reveal_from(&mut self, maze: &Maze, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let visible = match self.visibility {
+            Visibility::Radius(radius) => self.cells_in_radius(x, y, radius),
+            Visibility::LineOfSight => line_of_sight(maze, x, y),
+        };
+        let mut newly_revealed = Vec::new();
+        for (cx, cy) in visible {
+            let revealed = &mut self.revealed[cy * self.width + cx];
+            if !*revealed {
+                *revealed = true;
+                newly_revealed.push((cx, cy));
+            }
+        }
+        newly_revealed
+    }
+
+    fn cells_in_radius(&self, x: usize, y: usize, radius: usize) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        let r = radius as i32;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let cx = x as i32 + dx;
+                let cy = y as i32 + dy;
+                if cx >= 0 && cy >= 0 && cx < self.width as i32 && cy < self.height as i32 {
+                    cells.push((cx as usize, cy as usize));
+                }
+            }
+        }
+        cells
+    }
+}
+
+fn line_of_sight(maze: &Maze, x: usize, y: usize) -> Vec<(usize, usize)> {
+    let mut cells = vec![(x, y)];
+    for direction in Direction::ALL {
+        let mut current = (x, y);
+        // One cell at a time, so the crossings on the way are seen as well
+        while let Some(next) = maze.adjacent(current.0, current.1, direction) {
+            cells.push(next);
+            current = next;
+        }
+    }
+    cells
+}
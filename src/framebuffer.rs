@@ -0,0 +1,379 @@
+/// The frame buffer behind the HX8369, everything is drawn here first and `HX8369::flush`
+/// sends the dirty rows to the panel. It doesn't touch the hardware so it also runs on the host.
+use std::{
+    cmp::{max, min},
+    convert::Infallible,
+};
+
+use embedded_graphics::{
+    image::{Image, ImageDrawable},
+    pixelcolor::Rgb565,
+    prelude::{Dimensions, DrawTarget, Point, PointsIter, Size},
+    primitives::Rectangle,
+    Drawable,
+};
+
+#[derive(Debug)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    buffer: Vec<u16>,
+
+    min_dirty_y: usize,
+    max_dirty_y: usize,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            buffer: vec![0; width * height],
+            min_dirty_y: height,
+            max_dirty_y: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// First and last rows changed since the last `clear_dirty`, `None` if nothing changed.
+    /// The last row may be past the bottom of the screen after `invalidate`.
+    pub fn dirty_rows(&self) -> Option<(usize, usize)> {
+        if self.min_dirty_y > self.max_dirty_y {
+            return None;
+        }
+        Some((self.min_dirty_y, self.max_dirty_y))
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.min_dirty_y = self.height;
+        self.max_dirty_y = 0;
+    }
+
+    /// Mark the whole screen as dirty
+    pub fn invalidate(&mut self) {
+        self.min_dirty_y = 0;
+        self.max_dirty_y = self.height;
+    }
+
+    /// The pixels of rows `start..end`
+    pub fn rows(&self, start: usize, end: usize) -> &[u16] {
+        &self.buffer[start * self.width..end * self.width]
+    }
+
+    pub fn get_raw_buffer_mut<T>(&mut self) -> &mut [T] {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.buffer.as_mut_ptr() as *mut T,
+                self.buffer.len() * core::mem::size_of::<u16>() / core::mem::size_of::<T>(),
+            )
+        }
+    }
+
+    pub fn get_raw_buffer<T>(&self) -> &[T] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self.buffer.as_ptr() as *const T,
+                self.buffer.len() * core::mem::size_of::<u16>() / core::mem::size_of::<T>(),
+            )
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Rgb565 {
+        if x >= self.width || y >= self.height {
+            return <Rgb565 as embedded_graphics::prelude::RgbColor>::BLACK;
+        }
+        let w = self.width;
+        self.get_raw_buffer()[y * w + x]
+    }
+
+    /// Draw an image, e.g. an `ImageRaw` sprite.
+    /// Images are drawn with `fill_contiguous`, so whole rows are copied into the frame buffer.
+    pub fn blit<I>(&mut self, image: &I, top_left: Point)
+    where
+        I: ImageDrawable<Color = Rgb565>,
+    {
+        Image::new(image, top_left).draw(self).ok();
+    }
+
+    /// Copy RGB565 pixels in native order into the frame buffer, the fastest way to draw a sprite.
+    /// `pixels` is row-major with `width` pixels per row, the part outside of the screen is clipped.
+    pub fn blit_raw(&mut self, pixels: &[u16], width: usize, top_left: Point) {
+        if width == 0 {
+            return;
+        }
+        let height = pixels.len() / width;
+        let area = Rectangle::new(top_left, Size::new(width as u32, height as u32));
+        let Some((x, y, w, h)) = self.clip(&area) else {
+            return;
+        };
+        // Offset of the visible part inside of the sprite
+        let src_x = (x as i32 - top_left.x) as usize;
+        let src_y = (y as i32 - top_left.y) as usize;
+        let screen_width = self.width;
+        for row in 0..h {
+            let src = (src_y + row) * width + src_x;
+            let dst = (y + row) * screen_width + x;
+            self.buffer[dst..dst + w].copy_from_slice(&pixels[src..src + w]);
+        }
+        self.mark_dirty(y, y + h - 1);
+    }
+
+    /// Clip the area to the screen, returns `(x, y, width, height)` of the visible part
+    fn clip(&self, area: &Rectangle) -> Option<(usize, usize, usize, usize)> {
+        let area = self.bounding_box().intersection(area);
+        if area.is_zero_sized() {
+            return None;
+        }
+        Some((
+            area.top_left.x as usize,
+            area.top_left.y as usize,
+            area.size.width as usize,
+            area.size.height as usize,
+        ))
+    }
+
+    fn mark_dirty(&mut self, min_y: usize, max_y: usize) {
+        self.min_dirty_y = min(self.min_dirty_y, min_y);
+        self.max_dirty_y = max(self.max_dirty_y, max_y);
+    }
+}
+
+impl Dimensions for FrameBuffer {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(0, 0),
+            Size::new(self.width as u32, self.height as u32),
+        )
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = Rgb565;
+
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
+    {
+        // Record min and max dirty lines, the range in the middle will be flushed
+        // This is an optimization as the data transfer seems not to be fast enough, it takes around 4ms to flush the whole screen
+        let w = self.width;
+        let h = self.height;
+        let mut min_dirty_y = self.min_dirty_y;
+        let mut max_dirty_y = self.max_dirty_y;
+        let buffer = self.get_raw_buffer_mut();
+        for p in pixels {
+            if p.0.x < 0 || p.0.y < 0 {
+                continue;
+            }
+            let x = p.0.x as usize;
+            let y = p.0.y as usize;
+            if x >= w || y >= h {
+                continue;
+            }
+            min_dirty_y = min(min_dirty_y, y);
+            max_dirty_y = max(max_dirty_y, y);
+            buffer[y * w + x] = p.1;
+        }
+        self.min_dirty_y = min_dirty_y;
+        self.max_dirty_y = max_dirty_y;
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        // Only take the fast path if the whole area is on the screen, otherwise the colors
+        // of the clipped pixels need to be skipped one by one
+        if self.bounding_box().intersection(area) != *area {
+            return self.draw_iter(
+                area.points()
+                    .zip(colors)
+                    .map(|(p, c)| embedded_graphics::Pixel(p, c)),
+            );
+        }
+        let Some((x, y, w, h)) = self.clip(area) else {
+            return Ok(());
+        };
+        let width = self.width;
+        let mut colors = colors.into_iter();
+        let buffer = self.get_raw_buffer_mut::<Rgb565>();
+        for row in y..y + h {
+            let start = row * width + x;
+            for (p, c) in buffer[start..start + w].iter_mut().zip(colors.by_ref()) {
+                *p = c;
+            }
+        }
+        self.mark_dirty(y, y + h - 1);
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let Some((x, y, w, h)) = self.clip(area) else {
+            return Ok(());
+        };
+        let width = self.width;
+        let buffer = self.get_raw_buffer_mut::<Rgb565>();
+        for row in y..y + h {
+            let start = row * width + x;
+            buffer[start..start + w].fill(color);
+        }
+        self.mark_dirty(y, y + h - 1);
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.get_raw_buffer_mut::<Rgb565>().fill(color);
+        self.invalidate();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use embedded_graphics::{pixelcolor::RgbColor, transform::Transform, Pixel};
+
+    use super::*;
+    use crate::{maze::Maze, maze_painter::MazePainter, theme::Theme};
+
+    const WIDTH: usize = 800;
+    const HEIGHT: usize = 480;
+
+    /// Only has `draw_iter`, so everything goes through the default pixel by pixel paths
+    struct PixelTarget(FrameBuffer);
+
+    impl Dimensions for PixelTarget {
+        fn bounding_box(&self) -> Rectangle {
+            self.0.bounding_box()
+        }
+    }
+
+    impl DrawTarget for PixelTarget {
+        type Color = Rgb565;
+
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.0.draw_iter(pixels)
+        }
+    }
+
+    /// A full screen maze like the one of the game, with the cell size of the main config,
+    /// the walls drawn as lines or as blocks
+    fn painter(blocks: bool) -> MazePainter<Rgb565> {
+        let mut maze = Maze::new(38, 22);
+        maze.generate_seeded(0, 0, 7);
+        let theme = Theme::CLASSIC;
+        let painter = MazePainter::new(
+            maze,
+            theme.wall_style(),
+            Size::new(20, 20),
+            Point::new(25, 20),
+        )
+        .with_theme(&theme);
+        if blocks {
+            painter.with_blocks()
+        } else {
+            painter
+        }
+    }
+
+    fn redraw<D: DrawTarget<Color = Rgb565>>(painter: &MazePainter<Rgb565>, target: &mut D) {
+        target.clear(Theme::<Rgb565>::CLASSIC.background).ok();
+        painter.draw(target).ok();
+    }
+
+    #[test]
+    fn fast_paths_draw_the_same_pixels() {
+        for blocks in [false, true] {
+            let painter = painter(blocks);
+            let mut fast = FrameBuffer::new(WIDTH, HEIGHT);
+            let mut slow = PixelTarget(FrameBuffer::new(WIDTH, HEIGHT));
+            redraw(&painter, &mut fast);
+            redraw(&painter, &mut slow);
+            assert!(
+                fast.get_raw_buffer::<u16>() == slow.0.get_raw_buffer::<u16>(),
+                "blocks: {}",
+                blocks
+            );
+        }
+    }
+
+    #[test]
+    fn clipped_areas_draw_the_same_pixels() {
+        let mut fast = FrameBuffer::new(WIDTH, HEIGHT);
+        let mut slow = PixelTarget(FrameBuffer::new(WIDTH, HEIGHT));
+        let area = Rectangle::new(Point::new(-5, HEIGHT as i32 - 10), Size::new(30, 30));
+        let colors = (0..900).map(|i| Rgb565::new(i as u8 % 32, i as u8 % 64, 0));
+        fast.fill_contiguous(&area, colors.clone()).ok();
+        slow.fill_contiguous(&area, colors).ok();
+        fast.fill_solid(
+            &area.translate(Point::new(WIDTH as i32 - 20, 0)),
+            Rgb565::RED,
+        )
+        .ok();
+        slow.fill_solid(
+            &area.translate(Point::new(WIDTH as i32 - 20, 0)),
+            Rgb565::RED,
+        )
+        .ok();
+        assert!(fast.get_raw_buffer::<u16>() == slow.0.get_raw_buffer::<u16>());
+    }
+
+    #[test]
+    fn blit_raw_clips_and_marks_the_rows_dirty() {
+        let mut frame = FrameBuffer::new(WIDTH, HEIGHT);
+        assert_eq!(frame.dirty_rows(), None);
+        let sprite = [0xF800u16; 4 * 3];
+        frame.blit_raw(&sprite, 4, Point::new(-2, 10));
+        assert_eq!(frame.dirty_rows(), Some((10, 12)));
+        assert_eq!(frame.get_pixel(1, 10), Rgb565::RED);
+        assert_eq!(frame.get_pixel(2, 10), Rgb565::BLACK);
+        frame.clear_dirty();
+        frame.blit_raw(&sprite, 4, Point::new(WIDTH as i32, 0));
+        assert_eq!(frame.dirty_rows(), None);
+    }
+
+    /// Run with `cargo +stable test --release --lib --target <host triple> -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_full_screen_redraw() {
+        const ROUNDS: u32 = 50;
+        fn time<D: DrawTarget<Color = Rgb565>>(
+            painter: &MazePainter<Rgb565>,
+            target: &mut D,
+        ) -> Duration {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                redraw(painter, target);
+            }
+            start.elapsed() / ROUNDS
+        }
+        for (name, blocks) in [("lines", false), ("blocks", true)] {
+            let painter = painter(blocks);
+            let fast = time(&painter, &mut FrameBuffer::new(WIDTH, HEIGHT));
+            let slow = time(&painter, &mut PixelTarget(FrameBuffer::new(WIDTH, HEIGHT)));
+            println!(
+                "Full screen redraw with {}: {:?} with the fast paths, {:?} pixel by pixel ({:.1}x)",
+                name,
+                fast,
+                slow,
+                slow.as_secs_f64() / fast.as_secs_f64()
+            );
+        }
+    }
+}
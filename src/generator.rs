@@ -0,0 +1,160 @@
+/// Step-wise maze generation, one event at a time, so the maze can be drawn while it's built.
+/// A randomized depth-first search, see `Maze::generate_with_rng`.
+use rand::Rng;
+
+use crate::maze::Maze;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GenerationEvent {
+    /// The wall between the two cells was removed, `to` is visited for the first time
+    Carve {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// Nothing left to carve from the cell, going back
+    Backtrack((usize, usize)),
+}
+
+pub struct Generator<R: Rng> {
+    start: (usize, usize),
+    rng: R,
+    stack: Vec<(usize, usize)>,
+    // Cells joining the next part of the mask, still to be carved
+    bridge: Vec<(usize, usize)>,
+    started: bool,
+    done: bool,
+}
+
+impl<R: Rng> Generator<R> {
+    pub fn new(x: usize, y: usize, rng: R) -> Self {
+        Self {
+            start: (x, y),
+            rng,
+            stack: Vec::new(),
+            bridge: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Do one step of the generation, `None` once the maze is complete
+    pub fn step(&mut self, maze: &mut Maze) -> Option<GenerationEvent> {
+        if self.done {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            // The cells outside of the mask are never carved into
+            for cell in &mut maze.cells {
+                cell.visited = !cell.active;
+            }
+            let start = if maze[self.start].active {
+                Some(self.start)
+            } else {
+                maze.first_active_cell()
+            };
+            self.stack.extend(start);
+        }
+        loop {
+            // Carve the corridor to the next part of the mask, then continue from its end
+            if self.bridge.len() >= 2 {
+                let from = self.bridge.remove(0);
+                let to = self.bridge[0];
+                maze[to].active = true;
+                maze[to].visited = true;
+                maze.carve(from, to);
+                if self.bridge.len() == 1 {
+                    self.stack.push(self.bridge.remove(0));
+                }
+                return Some(GenerationEvent::Carve { from, to });
+            }
+            if let Some((x, y)) = self.stack.pop() {
+                maze[(x, y)].visited = true;
+                let mut neighbors = maze.get_cell_unvisited_neighbors_with_visited_neighbors(x, y);
+                if neighbors.is_empty() {
+                    return Some(GenerationEvent::Backtrack((x, y)));
+                }
+                self.stack.push((x, y));
+                // Choose a random unvisited neighbor and remove the wall to it
+                let next = neighbors.remove(self.rng.gen_range(0..neighbors.len()));
+                maze.carve((x, y), next);
+                maze[next].visited = true;
+                self.stack.push(next);
+                return Some(GenerationEvent::Carve {
+                    from: (x, y),
+                    to: next,
+                });
+            }
+            // Join the next part of the mask through the cells outside of it
+            match maze.bridge_to_unvisited() {
+                Some(bridge) => self.bridge = bridge,
+                None => break,
+            }
+        }
+        // Clear all visited flags
+        for cell in &mut maze.cells {
+            cell.visited = false;
+        }
+        self.done = true;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{mask::Mask, solver};
+
+    fn events(maze: &mut Maze, seed: u64) -> Vec<GenerationEvent> {
+        let mut generator = Generator::new(0, 0, StdRng::seed_from_u64(seed));
+        let events = std::iter::from_fn(|| generator.step(maze)).collect();
+        assert_eq!(generator.step(maze), None);
+        events
+    }
+
+    #[test]
+    fn carves_a_perfect_maze() {
+        let mut maze = Maze::new(9, 7);
+        let events = events(&mut maze, 4);
+        let carved = events
+            .iter()
+            .filter(|event| matches!(event, GenerationEvent::Carve { .. }))
+            .count();
+        // A tree joining all the cells
+        assert_eq!(carved, 9 * 7 - 1);
+        assert_eq!(solver::reachable(&maze, (0, 0), 0).len(), 9 * 7);
+        assert!(maze.cells.iter().all(|cell| !cell.visited));
+    }
+
+    #[test]
+    fn same_maze_as_generate() {
+        let mut stepped = Maze::new(9, 7);
+        events(&mut stepped, 4);
+        let mut generated = Maze::new(9, 7);
+        generated.generate_with_rng(0, 0, &mut StdRng::seed_from_u64(4));
+        for (a, b) in stepped.cells.iter().zip(&generated.cells) {
+            assert_eq!(
+                (a.north, a.south, a.east, a.west),
+                (b.north, b.south, b.east, b.west)
+            );
+        }
+    }
+
+    #[test]
+    fn joins_the_parts_of_a_mask() {
+        let mut maze = Maze::new(7, 2);
+        maze.apply_mask(&Mask::from_ascii("##...##\n##...##"));
+        let events = events(&mut maze, 1);
+        let start = maze.first_active_cell().unwrap();
+        assert_eq!(start, (0, 0));
+        // The corridor between the two parts is made of new cells
+        let active = maze.cells.iter().filter(|cell| cell.active).count();
+        assert!(active > 8);
+        assert_eq!(solver::reachable(&maze, start, 0).len(), active);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GenerationEvent::Carve { to: (3, _), .. })));
+    }
+}
@@ -0,0 +1,166 @@
+/// Turns the stream of touch samples from the GT911 into taps and swipes.
+///
+/// `read_touches` returns no touch both when the finger is lifted and when there is no new sample yet,
+/// so a gesture only ends after no touch has been seen for `release_timeout`.
+use std::time::{Duration, Instant};
+
+use embedded_graphics::prelude::Point;
+
+use crate::maze::Direction;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    Tap(Point),
+    Swipe(Direction),
+    /// Reported while the finger is still down, nothing more is reported for that touch
+    LongPress(Point),
+}
+
+#[derive(Clone, Debug)]
+pub struct GestureDetector {
+    start: Option<Point>,
+    started_at: Instant,
+    long_pressed: bool,
+    last: Point,
+    last_seen: Instant,
+    // Minimal distance in pixels for a touch to be recognized as a swipe instead of a tap
+    swipe_threshold: u32,
+    release_timeout: Duration,
+    // How long the finger has to stay still for a long press
+    long_press: Duration,
+}
+
+impl GestureDetector {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: None,
+            started_at: now,
+            long_pressed: false,
+            last: Point::zero(),
+            last_seen: now,
+            swipe_threshold: 30,
+            release_timeout: Duration::from_millis(60),
+            long_press: Duration::from_millis(800),
+        }
+    }
+
+    /// A finger is down, or was lifted less than the release timeout ago
+    pub fn is_touching(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Feed a touch sample, returns the gesture once the finger is lifted
+    pub fn update(&mut self, touch: Option<Point>, now: Instant) -> Option<Gesture> {
+        if let Some(point) = touch {
+            let start = match self.start {
+                Some(start) => start,
+                None => {
+                    self.start = Some(point);
+                    self.started_at = now;
+                    point
+                }
+            };
+            self.last = point;
+            self.last_seen = now;
+            let still = classify(start, point, self.swipe_threshold) == Gesture::Tap(start);
+            if still && !self.long_pressed && now - self.started_at >= self.long_press {
+                self.long_pressed = true;
+                return Some(Gesture::LongPress(start));
+            }
+            return None;
+        }
+        let start = self.start?;
+        if now.saturating_duration_since(self.last_seen) < self.release_timeout {
+            return None;
+        }
+        self.start = None;
+        if std::mem::take(&mut self.long_pressed) {
+            return None;
+        }
+        Some(classify(start, self.last, self.swipe_threshold))
+    }
+}
+
+fn classify(start: Point, end: Point, threshold: u32) -> Gesture {
+    let delta = end - start;
+    if delta.x.unsigned_abs().max(delta.y.unsigned_abs()) < threshold {
+        return Gesture::Tap(start);
+    }
+    // Pick the dominant axis
+    let direction = if delta.x.abs() >= delta.y.abs() {
+        if delta.x > 0 {
+            Direction::East
+        } else {
+            Direction::West
+        }
+    } else if delta.y > 0 {
+        Direction::South
+    } else {
+        Direction::North
+    };
+    Gesture::Swipe(direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Touch at each point 10ms apart then lift the finger, returns all the gestures
+    fn touch(points: &[(i32, i32)]) -> Vec<Gesture> {
+        let start = Instant::now();
+        let mut detector = GestureDetector::new(start);
+        let mut now = start;
+        let mut gestures = Vec::new();
+        for (x, y) in points {
+            gestures.extend(detector.update(Some(Point::new(*x, *y)), now));
+            assert!(detector.is_touching());
+            now += Duration::from_millis(10);
+        }
+        // No sample for a moment is not a release yet
+        assert_eq!(detector.update(None, now), None);
+        assert!(detector.is_touching());
+        gestures.extend(detector.update(None, now + Duration::from_millis(100)));
+        assert!(!detector.is_touching());
+        gestures
+    }
+
+    #[test]
+    fn small_moves_are_taps() {
+        assert_eq!(touch(&[(100, 100)]), [Gesture::Tap(Point::new(100, 100))]);
+        assert_eq!(
+            touch(&[(100, 100), (110, 80), (129, 120)]),
+            [Gesture::Tap(Point::new(100, 100))]
+        );
+    }
+
+    #[test]
+    fn swipes_go_along_the_longest_axis() {
+        assert_eq!(
+            touch(&[(100, 100), (140, 120)]),
+            [Gesture::Swipe(Direction::East)]
+        );
+        assert_eq!(
+            touch(&[(100, 100), (60, 90)]),
+            [Gesture::Swipe(Direction::West)]
+        );
+        assert_eq!(
+            touch(&[(100, 100), (120, 140)]),
+            [Gesture::Swipe(Direction::South)]
+        );
+        // Only where the finger was lifted counts
+        assert_eq!(
+            touch(&[(100, 100), (200, 100), (100, 40)]),
+            [Gesture::Swipe(Direction::North)]
+        );
+    }
+
+    #[test]
+    fn long_press_while_still_down() {
+        let points = vec![(100, 100); 100];
+        assert_eq!(touch(&points), [Gesture::LongPress(Point::new(100, 100))]);
+        // Moving away before the time is up is a swipe
+        let mut points = vec![(100, 100); 50];
+        points.extend(vec![(200, 100); 50]);
+        assert_eq!(touch(&points), [Gesture::Swipe(Direction::East)]);
+    }
+}
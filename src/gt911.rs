@@ -1,4 +1,5 @@
-/// A minimal implementation of the GT911 to work with Lvgl since Lvgl only uses a single touch point
+/// A minimal implementation of the GT911, originally written to work with Lvgl which only uses a single touch point,
+/// `read_touches` reports all the touch points for multi-touch use
 /// The default orientation and size are based on the aliexpress ESP 7 inch capactive touch development
 /// board model ESP-8048S070C
 use embedded_hal::{
@@ -9,11 +10,19 @@ use embedded_hal::{
 
 const DEFAULT_GT911_ADDRESS: u8 = 0x5d;
 
+/// The GT911 reports up to 5 touch points, each one takes 8 bytes starting from `Reg::Point1`
+const MAX_TOUCHES: u8 = 5;
+const POINT_STRIDE: u16 = 8;
+
+/// Value written to the command register to enter the sleep mode
+const COMMAND_SLEEP: u8 = 0x05;
+
 /// Documented registers of the device
 #[allow(dead_code)]
 #[repr(u16)]
 #[derive(Debug, Clone, Copy)]
 enum Reg {
+    Command = 0x8040,
     ProductId = 0x8140,
     PointInfo = 0x814E,
     Point1 = 0x814F,
@@ -21,7 +30,7 @@ enum Reg {
 
 /// Represents the orientation of the device
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Orientation {
     Portrait, // Do Not use
     Landscape,
@@ -150,6 +159,15 @@ where
         }
     }
 
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Map the touches for another orientation, e.g. from the settings
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
     pub fn reset(&mut self) -> Result<(), <RST as embedded_hal::digital::ErrorType>::Error> {
         //println!("======= Resetting GT911 =======");
         self.delay.delay_ms(5);
@@ -163,6 +181,23 @@ where
         Ok(())
     }
 
+    /// Put the GT911 into the sleep mode, it stops scanning and doesn't report touches until woken up
+    pub fn sleep(&mut self) -> Result<(), <I2C as embedded_hal::i2c::ErrorType>::Error> {
+        let command_reg: u16 = Reg::Command as u16;
+        let hi_byte: u8 = (command_reg >> 8).try_into().unwrap();
+        let lo_byte: u8 = (command_reg & 0xFF).try_into().unwrap();
+        let tx_buf: [u8; 3] = [hi_byte, lo_byte, COMMAND_SLEEP];
+        self.i2c.write(self.address, &tx_buf)
+    }
+
+    /// Wake up the GT911 from the sleep mode, the INT pin is not wired to us so do it with a reset
+    pub fn wake(&mut self) -> Result<(), <RST as embedded_hal::digital::ErrorType>::Error> {
+        self.reset()?;
+        // The GT911 needs ~50ms before it starts scanning again
+        self.delay.delay_ms(50);
+        Ok(())
+    }
+
     // Useful function to determine if you are communicating with GT911, The GT911 must first be reset.
     // The return string should be - 911
     pub fn read_product_id(
@@ -224,6 +259,37 @@ where
         Ok(if is_touched { Some(tp) } else { None })
     }
 
+    /// Read all the touch points, empty if there is no touch or no new data yet
+    pub fn read_touches(
+        &mut self,
+    ) -> Result<Vec<TouchPoint>, <I2C as embedded_hal::i2c::ErrorType>::Error> {
+        let mut rx_buf: [u8; 1] = [0xFF];
+
+        let point_info_reg: u16 = Reg::PointInfo as u16;
+        let hi_byte: u8 = (point_info_reg >> 8).try_into().unwrap();
+        let lo_byte: u8 = (point_info_reg & 0xFF).try_into().unwrap();
+        let tx_buf: [u8; 2] = [hi_byte, lo_byte];
+
+        self.i2c.write_read(self.address, &tx_buf, &mut rx_buf)?;
+
+        let point_info = rx_buf[0];
+        let buffer_status = point_info >> 7 & 1u8;
+        let touches = (point_info & 0xF).min(MAX_TOUCHES);
+
+        let mut points = Vec::with_capacity(touches as usize);
+        if buffer_status == 1 {
+            for i in 0..touches as u16 {
+                points.push(self.read_touch_point(Reg::Point1 as u16 + i * POINT_STRIDE)?);
+            }
+        }
+
+        // Reset point_info register after reading it
+        let tx_buf: [u8; 3] = [hi_byte, lo_byte, 0u8];
+        self.i2c.write(self.address, &tx_buf)?;
+
+        Ok(points)
+    }
+
     pub fn read_touch_point(
         &mut self,
         point_register: u16,
@@ -249,15 +315,15 @@ where
             Orientation::Portrait => {
                 let temp: u16 = x;
                 x = y;
-                y = self.size.height - temp;
+                y = self.size.height.saturating_sub(temp);
             }
             Orientation::InvertedLandscape => {
-                x = self.size.width - x;
-                y = self.size.height - y;
+                x = self.size.width.saturating_sub(x);
+                y = self.size.height.saturating_sub(y);
             }
             Orientation::InvertedPortrait => {
                 let temp: u16 = x;
-                x = self.size.width - y;
+                x = self.size.width.saturating_sub(y);
                 y = temp;
             }
         }
@@ -1,48 +1,225 @@
 use std::{
     cmp::{max, min},
     ffi::c_void,
+    thread,
+    time::Duration,
 };
 
 use std::convert::Infallible;
 
 use embedded_graphics::{
+    image::ImageDrawable,
     pixelcolor::Rgb565,
-    prelude::{Dimensions, DrawTarget, Point, Size},
+    prelude::{Dimensions, DrawTarget, Point},
     primitives::Rectangle,
 };
 use esp_idf_svc::sys::{
-    esp_lcd_panel_draw_bitmap, esp_lcd_panel_handle_t, esp_lcd_panel_invert_color,
-    esp_lcd_panel_mirror, esp_lcd_panel_reset, esp_lcd_panel_set_gap, esp_lcd_panel_swap_xy,
-    hx8369_init,
+    esp, esp_lcd_panel_draw_bitmap, esp_lcd_panel_handle_t, esp_lcd_panel_invert_color,
+    esp_lcd_panel_io_handle_t, esp_lcd_panel_io_tx_param, esp_lcd_panel_mirror,
+    esp_lcd_panel_reset, esp_lcd_panel_set_gap, esp_lcd_panel_swap_xy, hx8369_config_t,
+    hx8369_new_io, hx8369_new_panel, hx8369_start, EspError,
 };
 
-#[derive(Debug)]
-pub struct HX8369 {
-    handle: esp_lcd_panel_handle_t,
+use crate::{framebuffer::FrameBuffer, hx8369_init::InitSequence, power::DisplaySleep};
+
+// Standard MIPI DCS commands used to put the panel into and out of the sleep mode
+const CMD_SLPIN: u8 = 0x10;
+const CMD_SLPOUT: u8 = 0x11;
+const CMD_DISPOFF: u8 = 0x28;
+const CMD_DISPON: u8 = 0x29;
+
+/// The i80 bus on ESP32-S3 is at most 16 bits wide, matches `HX8369_MAX_BUS_WIDTH` in `hx8369.h`
+const MAX_BUS_WIDTH: usize = 16;
+
+/// Order of the color components the panel expects
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorOrder {
+    Rgb,
+    Bgr,
+}
 
+/// Board specific parameters of the HX8369 panel.
+/// The defaults are for the ESP-8048S070C board, other boards can override them with the builder methods.
+#[derive(Clone, Debug)]
+pub struct Hx8369Config {
+    data_pins: [i32; MAX_BUS_WIDTH],
+    bus_width: usize,
+    pclk_pin: i32,
+    cs_pin: i32,
+    dc_pin: i32,
+    reset_pin: i32,
     width: usize,
     height: usize,
-    buffer: Vec<u16>,
+    pixel_clock_hz: u32,
+    gap: (i32, i32),
+    color_order: ColorOrder,
+    invert_colors: bool,
+    swap_xy: bool,
+    mirror: (bool, bool),
+    init_sequence: InitSequence,
+}
+
+#[allow(dead_code)]
+impl Hx8369Config {
+    pub fn new() -> Self {
+        Self {
+            data_pins: [-1; MAX_BUS_WIDTH],
+            bus_width: 0,
+            pclk_pin: 10,
+            cs_pin: 12,
+            dc_pin: 11,
+            reset_pin: 9,
+            width: 800,
+            height: 480,
+            pixel_clock_hz: 20_000_000,
+            gap: (0, 0),
+            color_order: ColorOrder::Rgb,
+            invert_colors: false,
+            swap_xy: true,
+            mirror: (true, false),
+            init_sequence: InitSequence::new(),
+        }
+        .data_pins(&[46, 3, 8, 18, 17, 16, 15, 7])
+    }
+
+    /// Set the data pins, the bus width is the number of pins, either 8 or 16
+    pub fn data_pins(mut self, pins: &[i32]) -> Self {
+        assert!(
+            pins.len() == 8 || pins.len() == MAX_BUS_WIDTH,
+            "HX8369 bus width must be 8 or 16"
+        );
+        self.data_pins = [-1; MAX_BUS_WIDTH];
+        self.data_pins[..pins.len()].copy_from_slice(pins);
+        self.bus_width = pins.len();
+        self
+    }
+
+    pub fn pclk_pin(mut self, pin: i32) -> Self {
+        self.pclk_pin = pin;
+        self
+    }
+
+    pub fn cs_pin(mut self, pin: i32) -> Self {
+        self.cs_pin = pin;
+        self
+    }
+
+    pub fn dc_pin(mut self, pin: i32) -> Self {
+        self.dc_pin = pin;
+        self
+    }
+
+    /// Use -1 if the reset line is not connected, the panel will be reset with the SWRESET command
+    pub fn reset_pin(mut self, pin: i32) -> Self {
+        self.reset_pin = pin;
+        self
+    }
+
+    pub fn resolution(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn pixel_clock_hz(mut self, hz: u32) -> Self {
+        self.pixel_clock_hz = hz;
+        self
+    }
 
-    min_dirty_y: usize,
-    max_dirty_y: usize,
+    pub fn gap(mut self, x: i32, y: i32) -> Self {
+        self.gap = (x, y);
+        self
+    }
+
+    pub fn color_order(mut self, color_order: ColorOrder) -> Self {
+        self.color_order = color_order;
+        self
+    }
+
+    pub fn invert_colors(mut self, invert: bool) -> Self {
+        self.invert_colors = invert;
+        self
+    }
+
+    pub fn swap_xy(mut self, swap: bool) -> Self {
+        self.swap_xy = swap;
+        self
+    }
+
+    pub fn mirror(mut self, mirror_x: bool, mirror_y: bool) -> Self {
+        self.mirror = (mirror_x, mirror_y);
+        self
+    }
+
+    /// Vendor init sequence, replace it to use custom gamma/VCOM tables
+    pub fn init_sequence(mut self, init_sequence: InitSequence) -> Self {
+        self.init_sequence = init_sequence;
+        self
+    }
+
+    /// HX8369 can only send ~100K bytes at once, keep the transfer size at 100 lines of the short side
+    fn max_transfer_bytes(&self) -> usize {
+        min(self.width, self.height) * 100 * core::mem::size_of::<u16>()
+    }
+
+    fn to_raw(&self) -> hx8369_config_t {
+        hx8369_config_t {
+            data_gpio_nums: self.data_pins,
+            bus_width: self.bus_width,
+            pclk_gpio_num: self.pclk_pin,
+            cs_gpio_num: self.cs_pin,
+            dc_gpio_num: self.dc_pin,
+            rst_gpio_num: self.reset_pin,
+            h_res: self.width as i32,
+            v_res: self.height as i32,
+            pixel_clock_hz: self.pixel_clock_hz,
+            max_transfer_bytes: self.max_transfer_bytes(),
+            x_gap: self.gap.0,
+            y_gap: self.gap.1,
+            bgr_order: self.color_order == ColorOrder::Bgr,
+            invert_color: self.invert_colors,
+            swap_xy: self.swap_xy,
+            mirror_x: self.mirror.0,
+            mirror_y: self.mirror.1,
+        }
+    }
 }
 
-const LINES: usize = 60;
+impl Default for Hx8369Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct HX8369 {
+    handle: esp_lcd_panel_handle_t,
+    io: esp_lcd_panel_io_handle_t,
+    sleeping: bool,
+
+    frame: FrameBuffer,
+
+    // Number of lines can be sent in one transfer
+    lines: usize,
+}
 
 #[allow(dead_code)]
 impl HX8369 {
-    pub fn new(width: usize, height: usize) -> Self {
-        let handle = unsafe { hx8369_init() };
-        Self {
+    pub fn new(config: &Hx8369Config) -> Result<Self, EspError> {
+        let raw = config.to_raw();
+        let io = unsafe { hx8369_new_io(&raw) };
+        let handle = unsafe { hx8369_new_panel(io, &raw) };
+        send_init_sequence(io, &config.init_sequence)?;
+        unsafe { hx8369_start(handle, &raw) };
+        Ok(Self {
             handle,
-            width,
-            height,
-            buffer: vec![0; width * height],
+            io,
+            sleeping: false,
+            frame: FrameBuffer::new(config.width, config.height),
 
-            min_dirty_y: height,
-            max_dirty_y: 0,
-        }
+            lines: config.max_transfer_bytes() / (config.width * core::mem::size_of::<u16>()),
+        })
     }
 
     pub fn draw_bitmap<T: Sized>(
@@ -85,36 +262,77 @@ impl HX8369 {
         unsafe { esp_lcd_panel_invert_color(self.handle, invert) };
     }
 
+    fn tx_command(&self, command: u8) -> Result<(), EspError> {
+        esp!(unsafe { esp_lcd_panel_io_tx_param(self.io, command as i32, core::ptr::null(), 0) })
+    }
+
+    /// Turn off the display and put the panel into the sleep mode,
+    /// the frame buffer is kept so drawing can go on while sleeping
+    pub fn sleep(&mut self) -> Result<(), EspError> {
+        if self.sleeping {
+            return Ok(());
+        }
+        self.tx_command(CMD_DISPOFF)?;
+        self.tx_command(CMD_SLPIN)?;
+        // Need to wait 5ms before sending any new command
+        thread::sleep(Duration::from_millis(5));
+        self.sleeping = true;
+        Ok(())
+    }
+
+    /// Wake up the panel and restore the screen from the frame buffer
+    pub fn wake(&mut self) -> Result<(), EspError> {
+        if !self.sleeping {
+            return Ok(());
+        }
+        self.tx_command(CMD_SLPOUT)?;
+        // The panel needs 120ms to get out of the sleep mode
+        thread::sleep(Duration::from_millis(120));
+        self.tx_command(CMD_DISPON)?;
+        self.sleeping = false;
+        self.invalidate();
+        self.flush();
+        Ok(())
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
     pub fn get_width(&self) -> u32 {
-        self.width as u32
+        self.frame.width() as u32
     }
 
     pub fn get_height(&self) -> u32 {
-        self.height as u32
+        self.frame.height() as u32
     }
 
     pub fn invalidate(&mut self) {
-        self.min_dirty_y = 0;
-        self.max_dirty_y = self.height;
+        self.frame.invalidate();
     }
 
     pub fn flush(&mut self) {
-        // Nothing to flush
-        if self.min_dirty_y > self.max_dirty_y {
-            // Nothing to flush
+        // Keep the dirty range, the whole screen is flushed on wake anyway
+        if self.sleeping {
             return;
         }
+        let Some((min_dirty_y, max_dirty_y)) = self.frame.dirty_rows() else {
+            // Nothing to flush
+            return;
+        };
+        let width = self.frame.width();
+        let height = self.frame.height();
         // HX8369 can only send ~100K bytes at once, about 800x62 pixels in RGB565 format
-        // so we need to split the buffer into chunks, LINES is set to 60 as we have screen height at 480
-        // Flush in chunks of LINES
-        for i in (self.min_dirty_y..self.max_dirty_y).step_by(LINES) {
+        // so we need to split the buffer into chunks, `lines` is derived from the max transfer size in the config
+        // Flush in chunks of `lines`
+        for i in (min_dirty_y..max_dirty_y).step_by(self.lines) {
             unsafe {
                 // Don't exceed screen bounds, as well as max_y
-                let y_end = min(min(i + LINES, self.height), self.max_dirty_y + 1);
+                let y_end = min(min(i + self.lines, height), max_dirty_y + 1);
                 // Swap start and end if needed
                 let y_start = min(i, y_end);
                 let y_end = max(i, y_end);
-                let y_end = min(y_end, self.height);
+                let y_end = min(y_end, height);
                 // Skip if nothing to flush
                 if y_start >= y_end {
                     continue;
@@ -123,62 +341,84 @@ impl HX8369 {
                     self.handle,
                     0,
                     y_start as i32,
-                    self.width as i32,
+                    width as i32,
                     y_end as i32,
-                    (&self.buffer.as_slice()[y_start * self.width..y_end * self.width]) as *const _
-                        as *const c_void,
+                    self.frame.rows(y_start, y_end) as *const _ as *const c_void,
                 );
             }
         }
-        self.min_dirty_y = self.height;
-        self.max_dirty_y = 0;
+        self.frame.clear_dirty();
     }
 
     pub fn get_raw_buffer_mut<T>(&mut self) -> &mut [T] {
-        unsafe {
-            core::slice::from_raw_parts_mut(
-                self.buffer.as_mut_ptr() as *mut T,
-                self.buffer.len() * core::mem::size_of::<u16>() / core::mem::size_of::<T>(),
-            )
-        }
+        self.frame.get_raw_buffer_mut()
     }
 
     pub fn get_raw_buffer<T>(&self) -> &[T] {
-        unsafe {
-            core::slice::from_raw_parts_mut(
-                self.buffer.as_ptr() as *mut T,
-                self.buffer.len() * core::mem::size_of::<u16>() / core::mem::size_of::<T>(),
-            )
-        }
+        self.frame.get_raw_buffer()
     }
 
     pub fn get_pixel(&self, x: usize, y: usize) -> Rgb565 {
-        if x >= self.width || y >= self.height {
-            return <Rgb565 as embedded_graphics::prelude::RgbColor>::BLACK;
-        }
-        let w = self.width;
-        self.get_raw_buffer()[y * w + x]
+        self.frame.get_pixel(x, y)
     }
 
     pub fn fill(&mut self, color: Rgb565) {
-        self.min_dirty_y = 0;
-        self.max_dirty_y = self.height;
-        self.get_raw_buffer_mut()
-            .iter_mut()
-            .for_each(|p| *p = color);
+        self.clear(color).ok();
         self.flush();
     }
+
+    /// Draw an image, e.g. an `ImageRaw` sprite, see `FrameBuffer::blit`
+    pub fn blit<I>(&mut self, image: &I, top_left: Point)
+    where
+        I: ImageDrawable<Color = Rgb565>,
+    {
+        self.frame.blit(image, top_left);
+    }
+
+    /// Copy RGB565 pixels in native order into the frame buffer, see `FrameBuffer::blit_raw`
+    pub fn blit_raw(&mut self, pixels: &[u16], width: usize, top_left: Point) {
+        self.frame.blit_raw(pixels, width, top_left);
+    }
+}
+
+/// Send the vendor commands, the C driver does the rest of the panel init
+fn send_init_sequence(
+    io: esp_lcd_panel_io_handle_t,
+    sequence: &InitSequence,
+) -> Result<(), EspError> {
+    for c in sequence.commands() {
+        esp!(unsafe {
+            esp_lcd_panel_io_tx_param(
+                io,
+                c.command as i32,
+                c.params.as_ptr() as *const c_void,
+                c.params.len(),
+            )
+        })?;
+        if c.delay_ms > 0 {
+            thread::sleep(Duration::from_millis(c.delay_ms as u64));
+        }
+    }
+    Ok(())
+}
+
+impl DisplaySleep for HX8369 {
+    fn sleep(&mut self) -> anyhow::Result<()> {
+        Ok(HX8369::sleep(self)?)
+    }
+
+    fn wake(&mut self) -> anyhow::Result<()> {
+        Ok(HX8369::wake(self)?)
+    }
 }
 
 impl Dimensions for HX8369 {
     fn bounding_box(&self) -> embedded_graphics::primitives::Rectangle {
-        Rectangle::new(
-            Point::new(0, 0),
-            Size::new(self.width as u32, self.height as u32),
-        )
+        self.frame.bounding_box()
     }
 }
 
+// The drawing goes to the frame buffer, `flush` sends it to the panel
 impl DrawTarget for HX8369 {
     type Color = Rgb565;
 
@@ -188,28 +428,21 @@ impl DrawTarget for HX8369 {
     where
         I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
     {
-        // Record min and max dirty lines, the range in the middle will be flushed
-        // This is an optimization as the data transfer seems not to be fast enough, it takes around 4ms to flush the whole screen
-        for p in pixels {
-            if p.0.x < 0 || p.0.y < 0 {
-                continue;
-            }
-            let x = p.0.x as usize;
-            let y = p.0.y as usize;
-            if x >= self.width || y >= self.height {
-                continue;
-            }
-            if self.min_dirty_y > y {
-                self.min_dirty_y = y;
-            }
-            if self.max_dirty_y < y {
-                self.max_dirty_y = y;
-            }
-            if x < self.width && y < self.height {
-                let w = self.width;
-                self.get_raw_buffer_mut()[y * w + x] = p.1;
-            }
-        }
-        Ok(())
+        self.frame.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.frame.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.frame.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.frame.clear(color)
     }
 }
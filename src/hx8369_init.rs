@@ -0,0 +1,253 @@
+//! The vendor specific init sequence of HX8369, originally an opaque table in `hx8369.c`.
+//! The commands are sent by `HX8369::new` through the esp-idf LCD panel IO before the generic
+//! part of the panel init (SLPOUT, MADCTL and COLMOD), which is still done by the C driver.
+
+/// Vendor commands used in the init sequence
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Color LUT used to expand RGB565/RGB666 to the panel depth
+    ColorSet = 0x2D,
+    SetPower = 0xB1,
+    SetDisplay = 0xB2,
+    SetDisplayCycle = 0xB4,
+    SetVcom = 0xB6,
+    SetExtc = 0xB9,
+    SetDgc = 0xC1,
+    SetGip = 0xD5,
+    SetGamma = 0xE0,
+}
+
+/// A single command with its parameters, `delay_ms` is waited after sending it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitCommand {
+    pub command: Command,
+    pub params: Vec<u8>,
+    pub delay_ms: u32,
+}
+
+// Set_EXTC, unlocks the extended command set
+const SET_EXTC: [u8; 3] = [0xff, 0x83, 0x69];
+
+// Set Power
+const SET_POWER: [u8; 19] = [
+    0x01, 0x00, 0x34, 0x06, 0x00, 0x0f, 0x0f, 0x2a, 0x32, 0x3f, 0x3f, 0x07, 0x23, 0x01, 0xe6, 0xe6,
+    0xe6, 0xe6, 0xe6,
+];
+
+// SET Display 480x800, 0x2b;0x20-MCU;0x29-DPI;RM,DM; RM=0:DPI IF;  RM=1:RGB IF;
+const SET_DISPLAY: [u8; 15] = [
+    0x00, 0x20, 0x03, 0x03, 0x70, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00, 0x03, 0x03, 0x00, 0x01,
+];
+
+// SET Display CYC
+const SET_DISPLAY_CYCLE: [u8; 5] = [0x00, 0x0c, 0xa0, 0x0e, 0x06];
+
+// SET VCOM
+const DEFAULT_VCOM: [u8; 2] = [0x2c, 0x2c];
+
+// SET GIP
+const SET_GIP: [u8; 26] = [
+    0x00, 0x05, 0x03, 0x00, 0x01, 0x09, 0x10, 0x80, 0x37, 0x37, 0x20, 0x31, 0x46, 0x8a, 0x57, 0x9b,
+    0x20, 0x31, 0x46, 0x8a, 0x57, 0x9b, 0x07, 0x0f, 0x02, 0x00,
+];
+
+// SET GAMMA
+const DEFAULT_GAMMA: [u8; 34] = [
+    0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09, 0x0e, 0x0e, 0x12, 0x14, 0x12, 0x14, 0x13,
+    0x19, 0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09, 0x0e, 0x0e, 0x12, 0x14, 0x12, 0x14,
+    0x13, 0x19,
+];
+
+// set DGC
+const DEFAULT_DGC: [u8; 127] = [
+    0x01, 0x02, 0x08, 0x12, 0x1a, 0x22, 0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, 0x58, 0x60, 0x68, 0x70,
+    0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, 0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde, 0xe6, 0xef,
+    0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, 0x19, 0xec, 0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12, 0x1a, 0x22,
+    0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, 0x58, 0x60, 0x68, 0x70, 0x78, 0x80, 0x88, 0x90, 0x98, 0xa0,
+    0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde, 0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4,
+    0x19, 0xec, 0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12, 0x1a, 0x22, 0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51,
+    0x58, 0x60, 0x68, 0x70, 0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, 0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce,
+    0xd6, 0xde, 0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, 0x19, 0xec, 0x1b, 0x4c, 0x40,
+];
+
+/// Colour Set, the values wrap around at 256 exactly like the `uint8_t` table in the C driver did
+fn default_color_lut() -> Vec<u8> {
+    (0..192usize)
+        .map(|i| match i {
+            0..=63 => (i * 8) as u8,
+            64..=127 => (i * 4) as u8,
+            _ => (i * 8) as u8,
+        })
+        .collect()
+}
+
+/// The init sequence, gamma, VCOM and DGC tables can be replaced for panels with different glass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitSequence {
+    vcom: [u8; 2],
+    gamma: [u8; 34],
+    dgc: [u8; 127],
+}
+
+impl InitSequence {
+    pub fn new() -> Self {
+        Self {
+            vcom: DEFAULT_VCOM,
+            gamma: DEFAULT_GAMMA,
+            dgc: DEFAULT_DGC,
+        }
+    }
+
+    pub fn commands(&self) -> Vec<InitCommand> {
+        let command = |command, params: &[u8]| InitCommand {
+            command,
+            params: params.to_vec(),
+            delay_ms: 0,
+        };
+        vec![
+            command(Command::SetExtc, &SET_EXTC),
+            command(Command::SetPower, &SET_POWER),
+            command(Command::SetDisplay, &SET_DISPLAY),
+            command(Command::SetDisplayCycle, &SET_DISPLAY_CYCLE),
+            command(Command::SetVcom, &self.vcom),
+            command(Command::SetGip, &SET_GIP),
+            command(Command::SetGamma, &self.gamma),
+            command(Command::SetDgc, &self.dgc),
+            command(Command::ColorSet, &default_color_lut()),
+        ]
+    }
+}
+
+/// For panels with different glass, the board here uses the defaults
+#[allow(dead_code)]
+impl InitSequence {
+    /// VCOM voltage for forward and backward scan
+    pub fn vcom(mut self, forward: u8, backward: u8) -> Self {
+        self.vcom = [forward, backward];
+        self
+    }
+
+    /// Positive and negative gamma curves, 17 bytes each
+    pub fn gamma(mut self, gamma: [u8; 34]) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Digital gamma correction table
+    pub fn dgc(mut self, dgc: [u8; 127]) -> Self {
+        self.dgc = dgc;
+        self
+    }
+}
+
+impl Default for InitSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The byte stream put on the bus, each command byte followed by its parameters
+    fn to_bytes(sequence: &InitSequence) -> Vec<u8> {
+        sequence
+            .commands()
+            .into_iter()
+            .flat_map(|c| std::iter::once(c.command as u8).chain(c.params))
+            .collect()
+    }
+
+    /// The vendor part of `panel_hx8369_init` in the original `hx8369.c`, copied as it was
+    fn c_driver_bytes() -> Vec<u8> {
+        let mut bytes = vec![
+            0xB9, 0xFF, 0x83, 0x69, //
+            0xB1, 0x01, 0x00, 0x34, 0x06, 0x00, 0x0f, 0x0f, 0x2a, 0x32, 0x3f, //
+            0x3f, 0x07, 0x23, 0x01, 0xe6, 0xe6, 0xe6, 0xe6, 0xe6, //
+            0xB2, 0x00, 0x20, 0x03, 0x03, 0x70, 0x00, 0xff, 0x00, 0x00, 0x00, //
+            0x00, 0x03, 0x03, 0x00, 0x01, //
+            0xb4, 0x00, 0x0C, 0xA0, 0x0E, 0x06, //
+            0xb6, 0x2C, 0x2C, //
+            0xD5, 0x00, 0x05, 0x03, 0x00, 0x01, 0x09, 0x10, 0x80, 0x37, 0x37, 0x20, 0x31,
+            0x46, //
+            0x8a, 0x57, 0x9b, 0x20, 0x31, 0x46, 0x8a, 0x57, 0x9b, 0x07, 0x0f, 0x02, 0x00, //
+            0xE0, 0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09, 0x0e, 0x0e, 0x12,
+            0x14, //
+            0x12, 0x14, 0x13, 0x19, //
+            0x00, 0x08, 0x0d, 0x2d, 0x34, 0x3f, 0x19, 0x38, 0x09, 0x0e, 0x0e, 0x12, 0x14, //
+            0x12, 0x14, 0x13, 0x19, //
+            0xC1, 0x01, 0x02, 0x08, 0x12, 0x1a, 0x22, 0x2a, 0x31, //
+            0x36, 0x3f, 0x48, 0x51, 0x58, 0x60, 0x68, 0x70, //
+            0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, 0xa7, 0xaf, //
+            0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde, 0xe6, 0xef, //
+            0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, 0x19, 0xec, //
+            0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12, 0x1a, 0x22, //
+            0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, 0x58, 0x60, //
+            0x68, 0x70, 0x78, 0x80, 0x88, 0x90, 0x98, 0xa0, //
+            0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, 0xd6, 0xde, //
+            0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe, 0x8c, 0xa4, //
+            0x19, 0xec, 0x1b, 0x4c, 0x40, 0x02, 0x08, 0x12, //
+            0x1a, 0x22, 0x2a, 0x31, 0x36, 0x3f, 0x48, 0x51, //
+            0x58, 0x60, 0x68, 0x70, 0x78, 0x80, 0x88, 0x90, //
+            0x98, 0xa0, 0xa7, 0xaf, 0xb6, 0xbe, 0xc7, 0xce, //
+            0xd6, 0xde, 0xe6, 0xef, 0xf5, 0xfb, 0xfc, 0xfe, //
+            0x8c, 0xa4, 0x19, 0xec, 0x1b, 0x4c, 0x40, //
+            0x2D,
+        ];
+        // `uint8_t cmd_192[192]` filled by three loops
+        let mut cmd_192 = [0u8; 192];
+        for (i, value) in cmd_192.iter_mut().enumerate() {
+            let factor = if (64..=127).contains(&i) { 4 } else { 8 };
+            *value = (i * factor) as u8;
+        }
+        bytes.extend_from_slice(&cmd_192);
+        bytes
+    }
+
+    #[test]
+    fn default_sequence_matches_the_c_driver() {
+        assert_eq!(to_bytes(&InitSequence::new()), c_driver_bytes());
+    }
+
+    #[test]
+    fn parameter_counts_match_the_c_driver() {
+        let counts: Vec<_> = InitSequence::new()
+            .commands()
+            .iter()
+            .map(|c| (c.command as u8, c.params.len()))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (0xB9, 3),
+                (0xB1, 19),
+                (0xB2, 15),
+                (0xB4, 5),
+                (0xB6, 2),
+                (0xD5, 26),
+                (0xE0, 34),
+                (0xC1, 127),
+                (0x2D, 192),
+            ]
+        );
+    }
+
+    #[test]
+    fn replaced_tables_only_change_their_command() {
+        let gamma = [0x11; 34];
+        let sequence = InitSequence::new().vcom(0x30, 0x31).gamma(gamma);
+        for (custom, default) in sequence
+            .commands()
+            .iter()
+            .zip(InitSequence::new().commands())
+        {
+            match custom.command {
+                Command::SetVcom => assert_eq!(custom.params, [0x30, 0x31]),
+                Command::SetGamma => assert_eq!(custom.params, gamma),
+                _ => assert_eq!(*custom, default),
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+/// Idle policy of the device, decides when to dim the backlight and when to put the display to sleep,
+/// and the backlight fades. It only deals with timestamps so it doesn't depend on any hardware.
+use std::time::{Duration, Instant};
+
+/// Brightness levels are in percent
+pub const MAX_BRIGHTNESS: u8 = 100;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IdleState {
+    Active,
+    Dimmed,
+    Sleeping,
+}
+
+/// What the caller should do after feeding an event into the policy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IdleAction {
+    /// Fade the backlight to the given brightness
+    SetBrightness(u8),
+    /// Turn off the backlight and put the display into the low-power state
+    Sleep,
+    /// Wake up the display and restore the backlight to the given brightness
+    Wake(u8),
+}
+
+#[derive(Clone, Debug)]
+pub struct IdlePolicy {
+    dim_after: Duration,
+    sleep_after: Option<Duration>,
+    active_brightness: u8,
+    dimmed_brightness: u8,
+    last_activity: Instant,
+    state: IdleState,
+}
+
+impl IdlePolicy {
+    pub fn new(dim_after: Duration, now: Instant) -> Self {
+        Self {
+            dim_after,
+            sleep_after: None,
+            active_brightness: MAX_BRIGHTNESS,
+            dimmed_brightness: 10,
+            last_activity: now,
+            state: IdleState::Active,
+        }
+    }
+
+    /// Put the display to sleep after being idle for `sleep_after`, counted from the last activity
+    pub fn sleep_after(mut self, sleep_after: Duration) -> Self {
+        self.sleep_after = Some(sleep_after);
+        self
+    }
+
+    pub fn state(&self) -> IdleState {
+        self.state
+    }
+
+    pub fn set_active_brightness(&mut self, level: u8) -> Option<IdleAction> {
+        self.active_brightness = level;
+        match self.state {
+            IdleState::Active => Some(IdleAction::SetBrightness(level)),
+            _ => None,
+        }
+    }
+
+    /// Any user input, e.g. a touch, wakes the device up and restarts the idle timer
+    pub fn on_activity(&mut self, now: Instant) -> Option<IdleAction> {
+        self.last_activity = now;
+        let previous = self.state;
+        self.state = IdleState::Active;
+        match previous {
+            IdleState::Active => None,
+            IdleState::Dimmed => Some(IdleAction::SetBrightness(self.active_brightness)),
+            IdleState::Sleeping => Some(IdleAction::Wake(self.active_brightness)),
+        }
+    }
+
+    /// Called periodically, dims the backlight and then puts the display to sleep
+    /// once the device has been idle for long enough
+    pub fn tick(&mut self, now: Instant) -> Option<IdleAction> {
+        let idle = now.saturating_duration_since(self.last_activity);
+        match self.state {
+            IdleState::Active | IdleState::Dimmed
+                if self
+                    .sleep_after
+                    .is_some_and(|sleep_after| idle >= sleep_after) =>
+            {
+                self.state = IdleState::Sleeping;
+                Some(IdleAction::Sleep)
+            }
+            IdleState::Active if idle >= self.dim_after => {
+                self.state = IdleState::Dimmed;
+                Some(IdleAction::SetBrightness(self.dimmed_brightness))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A linear transition between two brightness levels
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fade {
+    from: u8,
+    to: u8,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Fade {
+    pub fn new(from: u8, to: u8, start: Instant, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            start,
+            duration,
+        }
+    }
+
+    /// Brightness at `now`, clamped to the target once the fade is over
+    pub fn level_at(&self, now: Instant) -> u8 {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed >= self.duration {
+            return self.to;
+        }
+        let from = self.from as i64;
+        let to = self.to as i64;
+        let level =
+            from + (to - from) * elapsed.as_millis() as i64 / self.duration.as_millis() as i64;
+        level as u8
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= self.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIM_AFTER: Duration = Duration::from_secs(30);
+    const SLEEP_AFTER: Duration = Duration::from_secs(120);
+
+    fn policy(start: Instant) -> IdlePolicy {
+        IdlePolicy::new(DIM_AFTER, start).sleep_after(SLEEP_AFTER)
+    }
+
+    #[test]
+    fn dims_then_sleeps_when_idle() {
+        let start = Instant::now();
+        let mut policy = policy(start);
+        assert_eq!(policy.tick(start + Duration::from_secs(29)), None);
+        assert_eq!(
+            policy.tick(start + DIM_AFTER),
+            Some(IdleAction::SetBrightness(10))
+        );
+        assert_eq!(policy.state(), IdleState::Dimmed);
+        assert_eq!(policy.tick(start + Duration::from_secs(60)), None);
+        assert_eq!(policy.tick(start + SLEEP_AFTER), Some(IdleAction::Sleep));
+        assert_eq!(policy.state(), IdleState::Sleeping);
+        assert_eq!(policy.tick(start + Duration::from_secs(200)), None);
+    }
+
+    #[test]
+    fn activity_wakes_up_and_restarts_the_timer() {
+        let start = Instant::now();
+        let mut policy = policy(start);
+        policy.tick(start + DIM_AFTER);
+        let touch = start + Duration::from_secs(40);
+        assert_eq!(
+            policy.on_activity(touch),
+            Some(IdleAction::SetBrightness(100))
+        );
+        assert_eq!(policy.state(), IdleState::Active);
+        assert_eq!(policy.tick(touch + Duration::from_secs(29)), None);
+        assert_eq!(
+            policy.tick(touch + DIM_AFTER),
+            Some(IdleAction::SetBrightness(10))
+        );
+
+        policy.tick(touch + SLEEP_AFTER);
+        let touch = touch + Duration::from_secs(300);
+        assert_eq!(policy.on_activity(touch), Some(IdleAction::Wake(100)));
+        assert_eq!(policy.on_activity(touch), None);
+    }
+
+    #[test]
+    fn sleeps_without_dimming_if_sleep_comes_first() {
+        let start = Instant::now();
+        let mut policy = IdlePolicy::new(DIM_AFTER, start).sleep_after(Duration::from_secs(10));
+        assert_eq!(
+            policy.tick(start + Duration::from_secs(10)),
+            Some(IdleAction::Sleep)
+        );
+    }
+
+    #[test]
+    fn never_sleeps_without_sleep_after() {
+        let start = Instant::now();
+        let mut policy = IdlePolicy::new(DIM_AFTER, start);
+        policy.tick(start + DIM_AFTER);
+        assert_eq!(policy.tick(start + Duration::from_secs(3600)), None);
+        assert_eq!(policy.state(), IdleState::Dimmed);
+    }
+
+    #[test]
+    fn active_brightness_applies_now_or_on_wake() {
+        let start = Instant::now();
+        let mut policy = policy(start);
+        assert_eq!(
+            policy.set_active_brightness(60),
+            Some(IdleAction::SetBrightness(60))
+        );
+        policy.tick(start + SLEEP_AFTER);
+        assert_eq!(policy.set_active_brightness(40), None);
+        assert_eq!(
+            policy.on_activity(start + Duration::from_secs(130)),
+            Some(IdleAction::Wake(40))
+        );
+    }
+
+    #[test]
+    fn fade_goes_from_one_end_to_the_other() {
+        let start = Instant::now();
+        let fade = Fade::new(100, 10, start, Duration::from_millis(500));
+        assert_eq!(fade.level_at(start), 100);
+        assert!(!fade.is_done(start));
+        assert_eq!(fade.level_at(start + Duration::from_millis(250)), 55);
+        assert_eq!(fade.level_at(start + Duration::from_millis(500)), 10);
+        assert!(fade.is_done(start + Duration::from_millis(500)));
+        assert_eq!(fade.level_at(start + Duration::from_secs(5)), 10);
+        // A sample taken before the fade started is still the starting level
+        assert_eq!(fade.level_at(start - Duration::from_millis(100)), 100);
+    }
+
+    #[test]
+    fn fade_up_and_instant_fade() {
+        let start = Instant::now();
+        let fade = Fade::new(0, 100, start, Duration::from_millis(100));
+        assert_eq!(fade.level_at(start + Duration::from_millis(10)), 10);
+        assert_eq!(fade.level_at(start + Duration::from_millis(100)), 100);
+        let instant = Fade::new(30, 70, start, Duration::ZERO);
+        assert_eq!(instant.level_at(start), 70);
+        assert!(instant.is_done(start));
+    }
+}
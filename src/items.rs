@@ -0,0 +1,189 @@
+/// Places coins, keys, doors, teleporters and one-way passages in a generated maze,
+/// the result is always checked with the solver so the maze stays solvable.
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    maze::{CellContent, Direction, Maze, MAX_KEYS},
+    solver,
+};
+
+/// How many of each item to place
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ItemConfig {
+    pub coins: usize,
+    /// Number of key/door pairs, at most `MAX_KEYS`
+    pub keys: usize,
+    /// Number of teleporter pairs
+    pub teleporters: usize,
+    pub one_way: usize,
+}
+
+// Give up placing a key or a teleporter pair after this many tries
+const ITEM_ATTEMPTS: usize = 10;
+
+fn direction_between(from: (usize, usize), to: (usize, usize)) -> Direction {
+    if to.0 > from.0 {
+        Direction::East
+    } else if to.0 < from.0 {
+        Direction::West
+    } else if to.1 > from.1 {
+        Direction::South
+    } else {
+        Direction::North
+    }
+}
+
+/// Empty cells of the maze other than the start and the goal
+fn free_cells(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    for y in 0..maze.height {
+        for x in 0..maze.width {
+            let cell = &maze[(x, y)];
+            if (x, y) != start
+                && (x, y) != goal
+                && cell.active
+                && cell.crossing.is_none()
+                && cell.content == CellContent::Empty
+            {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// Place the items, returns `false` if the maze has no solution, nothing is placed then
+pub fn place_items<R: Rng>(
+    maze: &mut Maze,
+    config: &ItemConfig,
+    start: (usize, usize),
+    goal: (usize, usize),
+    rng: &mut R,
+) -> bool {
+    let Some(path) = solver::solve(maze, start, goal) else {
+        return false;
+    };
+    // Cells on the solution path, without the start and the goal
+    let inner = if path.len() > 2 {
+        &path[1..path.len() - 1]
+    } else {
+        &[]
+    };
+
+    // One-way passages only on the solution path and only towards the goal,
+    // so the player can never get trapped
+    let mut indices: Vec<usize> = (0..inner.len()).collect();
+    indices.shuffle(rng);
+    for &i in indices.iter().take(config.one_way) {
+        let cell = inner[i];
+        let next = path[i + 2];
+        maze[cell].one_way = Some(direction_between(cell, next));
+    }
+
+    // Doors on the solution path in order, each key somewhere reachable with the keys before it.
+    // A key is moved until the goal can't be missed, e.g. the key left behind a one-way passage,
+    // the door is opened if there is no such place.
+    let keys = config.keys.min(MAX_KEYS as usize).min(inner.len());
+    let mut doors: Vec<usize> = rand::seq::index::sample(rng, inner.len(), keys).into_vec();
+    doors.sort();
+    for (key, &i) in doors.iter().enumerate() {
+        let key = key as u8;
+        let door = inner[i];
+        maze[door].content = CellContent::Door(key);
+        let mut candidates: Vec<(usize, usize)> = solver::reachable(maze, start, (1 << key) - 1)
+            .into_iter()
+            .filter(|cell| {
+                *cell != start && *cell != goal && maze[*cell].content == CellContent::Empty
+            })
+            .collect();
+        candidates.shuffle(rng);
+        let placed = candidates.iter().take(ITEM_ATTEMPTS).any(|&cell| {
+            maze[cell].content = CellContent::Key(key);
+            if solver::always_solvable(maze, start, goal) {
+                return true;
+            }
+            maze[cell].content = CellContent::Empty;
+            false
+        });
+        if !placed {
+            maze[door].content = CellContent::Empty;
+        }
+    }
+
+    // Teleporters anywhere, but they must not cut the way to the goal
+    for _ in 0..config.teleporters {
+        for _ in 0..ITEM_ATTEMPTS {
+            let free = free_cells(maze, start, goal);
+            let pair: Vec<&(usize, usize)> = free.choose_multiple(rng, 2).collect();
+            let [a, b] = pair[..] else {
+                break;
+            };
+            let (a, b) = (*a, *b);
+            maze[a].content = CellContent::Teleporter { to: b };
+            maze[b].content = CellContent::Teleporter { to: a };
+            if solver::always_solvable(maze, start, goal) {
+                break;
+            }
+            maze[a].content = CellContent::Empty;
+            maze[b].content = CellContent::Empty;
+        }
+    }
+
+    let free = free_cells(maze, start, goal);
+    for cell in free.choose_multiple(rng, config.coins) {
+        maze[*cell].content = CellContent::Coin;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn the_goal_can_always_be_reached() {
+        let config = ItemConfig {
+            coins: 10,
+            keys: MAX_KEYS as usize,
+            teleporters: 2,
+            one_way: 6,
+        };
+        let (start, goal) = ((0, 0), (15, 9));
+        for seed in 0..20 {
+            let mut maze = Maze::new(16, 10);
+            maze.generate_seeded(0, 0, seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            assert!(place_items(&mut maze, &config, start, goal, &mut rng));
+            assert!(solver::always_solvable(&maze, start, goal), "seed {}", seed);
+            // Every door that is left has its key
+            for cell in &maze.cells {
+                if let CellContent::Door(key) = cell.content {
+                    assert!(maze
+                        .cells
+                        .iter()
+                        .any(|c| c.content == CellContent::Key(key)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn places_keys_and_doors() {
+        let config = ItemConfig {
+            keys: 2,
+            ..Default::default()
+        };
+        let mut maze = Maze::new(16, 10);
+        maze.generate_seeded(0, 0, 5);
+        let mut rng = StdRng::seed_from_u64(5);
+        place_items(&mut maze, &config, (0, 0), (15, 9), &mut rng);
+        let count = |content| maze.cells.iter().filter(|c| c.content == content).count();
+        for key in 0..2 {
+            assert_eq!(count(CellContent::Key(key)), 1);
+            assert_eq!(count(CellContent::Door(key)), 1);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+/// A maze spanning several floors of the same size, connected by stairs.
+/// Each floor is a regular `Maze`, the stairs are the `up`/`down` flags of the cells.
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::maze::Maze;
+
+/// Position in a layered maze, `(x, y, floor)`
+pub type Cell3 = (usize, usize, usize);
+
+pub struct LayeredMaze {
+    pub width: usize,
+    pub height: usize,
+    pub floors: Vec<Maze>,
+}
+
+impl LayeredMaze {
+    pub fn new(width: usize, height: usize, floors: usize) -> Self {
+        Self {
+            width,
+            height,
+            floors: (0..floors).map(|_| Maze::new(width, height)).collect(),
+        }
+    }
+
+    /// Cells connected to the cell, on the same floor or through the stairs
+    pub fn passages(&self, (x, y, z): Cell3) -> Vec<Cell3> {
+        let maze = &self.floors[z];
+        let mut cells: Vec<Cell3> = maze
+            .open_neighbors(x, y)
+            .into_iter()
+            .map(|(nx, ny)| (nx, ny, z))
+            .collect();
+        if maze[(x, y)].up {
+            cells.push((x, y, z + 1));
+        }
+        if maze[(x, y)].down {
+            cells.push((x, y, z - 1));
+        }
+        cells
+    }
+
+    /// Generate each floor on its own and connect every two floors with `stairs` stairs,
+    /// put on cells without stairs yet so a cell never goes both up and down.
+    /// Every floor is a perfect maze, so the top floor can always be reached from any cell.
+    pub fn generate_with_rng<R: Rng>(&mut self, stairs: usize, rng: &mut R) {
+        for maze in &mut self.floors {
+            let x = rng.gen_range(0..self.width);
+            let y = rng.gen_range(0..self.height);
+            maze.generate_with_rng(x, y, rng);
+        }
+        for z in 1..self.floors.len() {
+            let free: Vec<(usize, usize)> = (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .filter(|&cell| !self.floors[z - 1][cell].down)
+                .collect();
+            for &cell in free.choose_multiple(rng, stairs.max(1)) {
+                self.floors[z - 1][cell].up = true;
+                self.floors[z][cell].down = true;
+            }
+        }
+    }
+
+    /// Shortest path between two cells, possibly over several floors
+    pub fn solve(&self, start: Cell3, goal: Cell3) -> Option<Vec<Cell3>> {
+        let index = |(x, y, z): Cell3| (z * self.height + y) * self.width + x;
+        let mut previous: Vec<Option<Cell3>> =
+            vec![None; self.width * self.height * self.floors.len()];
+        let mut seen = vec![false; previous.len()];
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        seen[index(start)] = true;
+        while let Some(cell) = queue.pop_front() {
+            if cell == goal {
+                let mut path = vec![cell];
+                let mut current = cell;
+                while let Some(prev) = previous[index(current)] {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.passages(cell) {
+                if !seen[index(next)] {
+                    seen[index(next)] = true;
+                    previous[index(next)] = Some(cell);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// Two floors with a corridor each, stairs at the east end of the bottom one
+    fn two_corridors() -> LayeredMaze {
+        let mut maze = LayeredMaze::new(3, 1, 2);
+        for floor in &mut maze.floors {
+            floor.carve((0, 0), (1, 0));
+            floor.carve((1, 0), (2, 0));
+        }
+        maze.floors[0][(2, 0)].up = true;
+        maze.floors[1][(2, 0)].down = true;
+        maze
+    }
+
+    #[test]
+    fn takes_the_stairs_to_the_top_floor() {
+        let maze = two_corridors();
+        assert_eq!(
+            maze.solve((0, 0, 0), (0, 0, 1)),
+            Some(vec![
+                (0, 0, 0),
+                (1, 0, 0),
+                (2, 0, 0),
+                (2, 0, 1),
+                (1, 0, 1),
+                (0, 0, 1)
+            ])
+        );
+    }
+
+    #[test]
+    fn no_way_up_without_stairs() {
+        let mut maze = two_corridors();
+        maze.floors[0][(2, 0)].up = false;
+        maze.floors[1][(2, 0)].down = false;
+        assert!(maze.solve((0, 0, 0), (0, 0, 1)).is_none());
+    }
+
+    #[test]
+    fn generated_floors_reach_the_top() {
+        let mut maze = LayeredMaze::new(8, 6, 3);
+        maze.generate_with_rng(2, &mut StdRng::seed_from_u64(5));
+        let path = maze.solve((0, 0, 0), (7, 5, 2)).unwrap();
+        for step in path.windows(2) {
+            assert!(maze.passages(step[0]).contains(&step[1]));
+        }
+        for z in 1..3 {
+            let stairs = |floor: &Maze| {
+                (0..6)
+                    .flat_map(|y| (0..8).map(move |x| (x, y)))
+                    .filter(|&cell| floor[cell].up)
+                    .count()
+            };
+            assert_eq!(stairs(&maze.floors[z - 1]), 2);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+/// Shows one floor of a `LayeredMaze` at a time, tapping the stairs of an explored cell
+/// switches to the floor above or below.
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::PrimitiveStyle,
+    Drawable,
+};
+use log::info;
+
+use crate::{layered_maze::LayeredMaze, maze_painter::MazePainter, path::PathStyle};
+
+pub struct LayeredPainter<C: PixelColor> {
+    /// One painter per floor, from the bottom up
+    pub painters: Vec<MazePainter<C>>,
+    floor: usize,
+    path_style: PathStyle<C>,
+    background: C,
+}
+
+impl<C: PixelColor> LayeredPainter<C> {
+    /// The exploration starts at the top left corner of the bottom floor,
+    /// the goal is the bottom right corner of the top floor
+    pub fn new(
+        maze: LayeredMaze,
+        style: PrimitiveStyle<C>,
+        path_style: PathStyle<C>,
+        background: C,
+        cell_size: Size,
+        offset: Point,
+    ) -> Self {
+        let painters = maze
+            .floors
+            .into_iter()
+            .enumerate()
+            .map(|(floor, maze)| {
+                let mut painter =
+                    MazePainter::new(maze, style, cell_size, offset).without_markers();
+                painter.maze[(0, 0)].visited = floor == 0;
+                painter
+            })
+            .collect();
+        Self {
+            painters,
+            floor: 0,
+            path_style,
+            background,
+        }
+    }
+
+    pub fn floors(&self) -> usize {
+        self.painters.len()
+    }
+
+    /// The goal is on the top floor
+    pub fn is_solved(&self) -> bool {
+        self.painters[self.floors() - 1].is_solved()
+    }
+
+    /// Clear the screen and draw another floor with the path explored on it
+    pub fn show_floor<D>(&mut self, floor: usize, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.floor = floor;
+        info!("Showing floor {}", floor);
+        target.clear(self.background).ok();
+        self.draw(target).ok();
+    }
+
+    /// Extend the path on the visible floor, or take the stairs of an explored cell:
+    /// the upper half of the cell goes up and the lower half goes down.
+    /// Returns true if anything changed on the screen.
+    pub fn on_click<D>(&mut self, x: i32, y: i32, target: &mut D) -> bool
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let floor = self.floor;
+        let painter = &mut self.painters[floor];
+        if painter.on_click(x, y, self.path_style, target) {
+            self.sync_stairs(floor);
+            return true;
+        }
+        let Some((cx, cy)) = painter.point_to_cell(x, y) else {
+            return false;
+        };
+        let cell = &painter.maze[(cx, cy)];
+        if !cell.visited {
+            return false;
+        }
+        let (_, center_y) = painter.get_cell_central_point(cx, cy);
+        if y < center_y && cell.up {
+            self.show_floor(floor + 1, target);
+            true
+        } else if y >= center_y && cell.down {
+            self.show_floor(floor - 1, target);
+            true
+        } else {
+            false
+        }
+    }
+
+    // The cells at the other end of the explored stairs get explored as well
+    fn sync_stairs(&mut self, floor: usize) {
+        let maze = &self.painters[floor].maze;
+        let mut reached = Vec::new();
+        for y in 0..maze.height {
+            for x in 0..maze.width {
+                let cell = &maze[(x, y)];
+                if !cell.visited {
+                    continue;
+                }
+                if cell.up {
+                    reached.push((x, y, floor + 1));
+                }
+                if cell.down {
+                    reached.push((x, y, floor - 1));
+                }
+            }
+        }
+        for (x, y, z) in reached {
+            self.painters[z].maze[(x, y)].visited = true;
+        }
+    }
+}
+
+impl<C: PixelColor> Drawable for LayeredPainter<C> {
+    type Color = C;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let painter = &self.painters[self.floor];
+        painter.draw(target)?;
+        painter.draw_path(&self.path_style, target);
+        if self.floor == 0 {
+            painter.draw_marker(
+                painter.start.0,
+                painter.start.1,
+                &painter.start_style,
+                target,
+            );
+        }
+        if self.floor == self.floors() - 1 {
+            painter.draw_marker(painter.goal.0, painter.goal.1, &painter.goal_style, target);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    #[test]
+    fn stairs_explore_the_other_floor() {
+        let mut maze = LayeredMaze::new(2, 1, 2);
+        for floor in &mut maze.floors {
+            floor.carve((0, 0), (1, 0));
+        }
+        maze.floors[0][(1, 0)].up = true;
+        maze.floors[1][(1, 0)].down = true;
+        let mut painter = LayeredPainter::new(
+            maze,
+            PrimitiveStyle::with_stroke(Rgb565::WHITE, 1),
+            PathStyle::solid(Rgb565::GREEN, 3),
+            Rgb565::BLACK,
+            Size::new(10, 10),
+            Point::zero(),
+        );
+        let mut target = FrameBuffer::new(20, 10);
+        assert!(!painter.painters[1].maze[(1, 0)].visited);
+        // Explore the cell with the stairs on the bottom floor
+        assert!(painter.on_click(15, 5, &mut target));
+        assert!(painter.painters[1].maze[(1, 0)].visited);
+        assert!(!painter.painters[1].maze[(0, 0)].visited);
+        // The upper half of the cell takes them up
+        assert!(painter.on_click(15, 2, &mut target));
+        assert_eq!(painter.floor, 1);
+    }
+}
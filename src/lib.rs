@@ -0,0 +1,38 @@
+// The modules that don't depend on ESP-IDF, built for the host to run their tests with
+// `cargo +stable test --lib --target <host triple>`, e.g. `x86_64-unknown-linux-gnu`.
+// The firmware is the binary, see `main.rs`.
+#![cfg(test)]
+// The tests only use part of each module, the firmware uses the rest. The binary has no
+// such allow, its build is the one that reports dead code.
+#![allow(dead_code)]
+
+mod attract;
+mod blocks;
+mod challenge;
+mod clock;
+mod dpad;
+mod enemy;
+mod fog;
+mod framebuffer;
+mod generator;
+mod gesture;
+mod gt911;
+mod hx8369_init;
+mod idle;
+mod items;
+mod layered_maze;
+mod layered_painter;
+mod mask;
+mod maze;
+mod maze_painter;
+mod path;
+mod power;
+mod runner;
+mod search;
+mod settings;
+mod solver;
+mod text;
+mod theme;
+mod walls;
+mod weave;
+mod widget;
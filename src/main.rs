@@ -1,34 +1,147 @@
-use std::{thread, time::Duration};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
 
+use attract::Attract;
+use challenge::{Challenge, ChallengeMode, ChallengeState, Leaderboard};
+use clock::{Clock, SystemClock};
+use dpad::DPad;
 use embedded_graphics::Drawable;
 use embedded_graphics::{
+    draw_target::DrawTarget,
     geometry::{Point, Size},
-    pixelcolor::{Rgb565, RgbColor},
-    primitives::PrimitiveStyleBuilder,
+    pixelcolor::{Rgb565, RgbColor, WebColors},
+    primitives::{Primitive, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle},
+    text::Alignment,
 };
-use esp_idf_svc::hal::{
-    delay::Ets,
-    gpio::PinDriver,
-    i2c::{I2cConfig, I2cDriver},
-    peripherals::Peripherals,
-    units::FromValueType,
+use enemy::{Behavior, Enemies, Enemy};
+use esp_idf_svc::{
+    hal::{
+        delay::Ets,
+        gpio::PinDriver,
+        i2c::{I2cConfig, I2cDriver},
+        ledc::{config::TimerConfig, LedcDriver, LedcTimerDriver},
+        peripherals::Peripherals,
+        units::FromValueType,
+    },
+    nvs::EspDefaultNvsPartition,
 };
+use generator::{GenerationEvent, Generator};
+use gesture::{Gesture, GestureDetector};
 use gt911::GT911Builder;
+use hx8369::{Hx8369Config, HX8369};
+use idle::{IdlePolicy, IdleState};
+use items::ItemConfig;
+use layered_maze::LayeredMaze;
+use layered_painter::LayeredPainter;
 use log::info;
-use maze_painter::MazePainter;
+use maze_painter::{ItemStyle, MazePainter, SearchStyle};
+use path::PathStyle;
+use race::Race;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use runner::Runner;
+use settings::{Algorithm, Difficulty, Settings, SettingsEvent, SettingsScreen};
+use settings_store::SettingsStore;
+use text::{Label, TextStyle, VerticalAlignment};
+use theme::Theme;
+use widget::WidgetStyle;
 
+mod attract;
+mod backlight;
+mod blocks;
+mod challenge;
+mod clock;
+mod dpad;
+mod enemy;
+mod fog;
+mod framebuffer;
+mod generator;
+mod gesture;
 mod gt911;
 mod hx8369;
+mod hx8369_init;
+mod idle;
+mod items;
+mod layered_maze;
+mod layered_painter;
+mod mask;
 mod maze;
 mod maze_painter;
+mod path;
+mod power;
+mod race;
+mod runner;
+mod search;
+mod settings;
+mod settings_store;
+mod solver;
+mod text;
+mod theme;
+mod walls;
+mod weave;
+mod widget;
 
 const SCREEN_WIDTH: usize = 800;
 const SCREEN_HEIGHT: usize = 480;
+// Cell size and maze sizes of the hardest difficulty, the easier ones have bigger cells
 const CELL_SIZE: usize = 20;
 const MAZE_WIDTH: usize = 38;
 const MAZE_HEIGHT: usize = 22;
+// Narrower maze in the runner mode to leave space for the D-pad on the right
+const RUNNER_MAZE_WIDTH: usize = 30;
+// Each player of the race gets half of the screen
+const RACE_MAZE_WIDTH: usize = 18;
+const RACE_RESTART_DELAY: Duration = Duration::from_secs(3);
+const FLOORS: usize = 3;
+// Stairs between every two floors
+const STAIRS: usize = 3;
+const DPAD_CENTER: Point = Point::new(715, 240);
+const DPAD_BUTTON_SIZE: u32 = 50;
 const X_OFFSET: u16 = 25;
 const Y_OFFSET: u16 = 20;
+const DIM_AFTER: Duration = Duration::from_secs(30);
+const SLEEP_AFTER: Duration = Duration::from_secs(120);
+const FADE_DURATION: Duration = Duration::from_millis(500);
+// The solvers take turns on a maze while the screen is dimmed, in the tap and runner modes
+const ATTRACT_MODE: bool = true;
+const ATTRACT_STEP: Duration = Duration::from_millis(20);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PlayMode {
+    /// Tap the next cell to extend the path
+    Tap,
+    /// Move a token with swipes or the D-pad
+    Runner,
+    /// Two players race on the same maze side by side
+    Race,
+    /// Tap mode on a maze spanning several floors connected by stairs
+    Floors,
+}
+
+const PLAY_MODE: PlayMode = PlayMode::Tap;
+const LEADERBOARD_SIZE: usize = 5;
+// Put the start and the goal at the two ends of the longest path instead of the corners,
+// for the hardest maze
+const LONGEST_PATH: bool = false;
+// Set to e.g. `Some(Duration::from_millis(5))` to show the maze being built,
+// the time between two carved passages, not used for weave mazes
+const GENERATION_DELAY: Option<Duration> = None;
+// The chance of each cell of a weave maze to be a crossing where one corridor passes
+// under another one, the parts of a mask must touch then
+const WEAVE_DENSITY: f64 = 0.3;
+// Placed in the maze when the items are turned on in the settings
+const ITEMS: ItemConfig = ItemConfig {
+    coins: 10,
+    keys: 2,
+    teleporters: 1,
+    one_way: 3,
+};
+const ENEMY_PERIOD: Duration = Duration::from_millis(400);
+// Manhattan distance from the start the enemies spawn at
+const ENEMY_MIN_DISTANCE: usize = 15;
+// Set to `true` to draw the walls as blocks of a tile grid instead of lines
+const BLOCKS: bool = false;
 
 fn main() -> anyhow::Result<()> {
     // It is necessary to call this function once. Otherwise some patches to the runtime
@@ -48,56 +161,774 @@ fn main() -> anyhow::Result<()> {
     let scl = pins.gpio38;
     let config = I2cConfig::new().baudrate(100.kHz().into());
     let i2c = I2cDriver::new(i2c, sda, scl, &config)?;
+    // Without the NVS partition the game still runs, the settings just aren't kept
+    let mut settings_store = match EspDefaultNvsPartition::take().and_then(SettingsStore::new) {
+        Ok(store) => Some(store),
+        Err(err) => {
+            info!("Can't open the settings storage: {:?}", err);
+            None
+        }
+    };
+    let mut settings = settings_store
+        .as_ref()
+        .map_or_else(Settings::default, SettingsStore::load);
+    info!("Settings: {:?}", settings);
+
     let rst = PinDriver::output(pins.gpio4)?; // reset pin on GT911
     let builder = GT911Builder::new(i2c, rst, Ets)
         .address(0x5d)
-        .orientation(gt911::Orientation::InvertedPortrait)
+        .orientation(settings.orientation)
         .size(SCREEN_WIDTH as u16, SCREEN_HEIGHT as u16);
     let mut touch_screen = builder.build();
 
-    // The board needs to set the pin 6 to high before resetting the touch screen
-    PinDriver::output(pins.gpio6)?.set_high()?;
+    // The board needs to set the pin 6 to high before resetting the touch screen,
+    // it's also the backlight pin, the backlight starts at full brightness
+    let backlight_timer = LedcTimerDriver::new(
+        peripherals.ledc.timer0,
+        &TimerConfig::default().frequency(25.kHz().into()),
+    )?;
+    let backlight_driver = LedcDriver::new(peripherals.ledc.channel0, backlight_timer, pins.gpio6)?;
+    let mut backlight = backlight::Backlight::new(backlight_driver)?;
     thread::sleep(Duration::from_millis(5));
     touch_screen.reset()?;
 
-    let mut display = hx8369::HX8369::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let panel_config = Hx8369Config::new().resolution(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut display = HX8369::new(&panel_config)?;
 
     display.fill(Rgb565::BLACK);
 
-    let mut maze = maze::Maze::new(MAZE_WIDTH, MAZE_HEIGHT);
-    maze.generate(0, 0);
+    let dpad = DPad::new(
+        DPAD_CENTER,
+        DPAD_BUTTON_SIZE,
+        PrimitiveStyleBuilder::new()
+            .stroke_color(Rgb565::WHITE)
+            .stroke_width(1)
+            .build(),
+        PrimitiveStyle::with_fill(Rgb565::WHITE),
+    );
+
+    let clock = SystemClock::new();
+    let mut leaderboard = Leaderboard::new(LEADERBOARD_SIZE);
+    let mut challenge = settings
+        .challenge_mode()
+        .map(|mode| Challenge::new(mode, rand::random(), &clock));
+
+    let (width, height) = maze_size(&challenge, settings.difficulty);
+    let seed = challenge.as_ref().map(|challenge| challenge.seed());
+    let mut theme = Theme::ALL[settings.theme];
+    let (mut painter, mut runner, mut enemies) =
+        new_round(width, height, seed, &settings, &theme, &dpad, &mut display);
+    // What the current maze was generated with
+    let mut round_settings = settings;
+    let mut race = if PLAY_MODE == PlayMode::Race {
+        Some(new_race(&mut display))
+    } else {
+        None
+    };
+    let mut race_won_at = None;
+    let mut floors = if PLAY_MODE == PlayMode::Floors {
+        Some(new_floors(&mut display))
+    } else {
+        None
+    };
+
+    let idle_policy = IdlePolicy::new(DIM_AFTER, Instant::now()).sleep_after(SLEEP_AFTER);
+    let mut power_manager = power::PowerManager::new(idle_policy, FADE_DURATION, Instant::now());
+    power_manager.set_brightness(
+        settings.brightness,
+        Instant::now(),
+        &mut display,
+        &mut backlight,
+        &mut touch_screen,
+    )?;
+    let mut gestures = GestureDetector::new(Instant::now());
+    let mut attract: Option<Attract<Rgb565>> = None;
+    let mut settings_screen: Option<SettingsScreen<Rgb565>> = None;
+    // The finger opening the settings is still down, it doesn't touch the screen yet
+    let mut opening_settings = false;
+    // The game doesn't go on while the settings or the demo are shown, since when
+    let mut paused_at = Instant::now();
+    // The touch started around the maze, it's not a move
+    let mut outside = false;
+    // The touch started on a visited cell, tapping the end of the route takes it back
+    let mut undo = None;
+
+    loop {
+        // The touch screen sleeps with the display, it's only read while awake
+        let touches = if power_manager.touch_awake() {
+            touch_screen.read_touches()?
+        } else {
+            Vec::new()
+        };
+        let touch = touches.first().copied();
+        let now = Instant::now();
+        let woken = if touch.is_some() {
+            power_manager.on_touch(now, &mut display, &mut backlight, &mut touch_screen)?
+        } else {
+            false
+        };
+        power_manager.tick(now, &mut display, &mut backlight, &mut touch_screen)?;
+
+        // The game stays paused while the display sleeps too, the demo just stops moving
+        let idle = power_manager.state() != IdleState::Active;
+        let sleeping = power_manager.state() == IdleState::Sleeping;
+        if ATTRACT_MODE
+            && settings_screen.is_none()
+            && matches!(PLAY_MODE, PlayMode::Tap | PlayMode::Runner)
+        {
+            match (&mut attract, idle) {
+                (None, true) => {
+                    if let Some(current) = &mut challenge {
+                        current.pause();
+                    }
+                    paused_at = now;
+                    let demo = new_attract(&theme, now);
+                    demo.draw(&mut display);
+                    display.flush();
+                    attract = Some(demo);
+                }
+                (Some(demo), true) => {
+                    if !sleeping && demo.update(now, &mut display) {
+                        display.flush();
+                    }
+                }
+                (Some(_), false) => {
+                    attract = None;
+                    if let Some(current) = &mut challenge {
+                        current.resume();
+                    }
+                    runner.delay(now - paused_at);
+                    enemies.delay(now - paused_at);
+                    redraw_round(
+                        &painter,
+                        &mut runner,
+                        &mut enemies,
+                        &theme,
+                        &dpad,
+                        &mut display,
+                    );
+                    // The touch ending the demo is not a move
+                    continue;
+                }
+                (None, false) => {}
+            }
+        }
+        // The game is paused during the demo
+        if attract.is_some() {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
+        // The touch used to wake up the screen is not a move
+        if woken {
+            continue;
+        }
+        let point = touch.map(|point| Point::new(point.x as i32, point.y as i32));
+        let touch_started = point.is_some() && !gestures.is_touching();
+        let gesture = gestures.update(point, now);
+
+        // The game is paused while the settings are shown
+        if let Some(screen) = &mut settings_screen {
+            opening_settings = opening_settings && gestures.is_touching();
+            let event = if opening_settings {
+                None
+            } else {
+                screen.on_touch(touch, now)
+            };
+            if let Some(event) = event {
+                info!("Settings {:?}: {:?}", event, screen.settings);
+                settings = screen.settings;
+                apply_settings(
+                    &settings,
+                    &mut theme,
+                    &mut painter,
+                    &mut runner,
+                    &mut enemies,
+                );
+                touch_screen.set_orientation(settings.orientation);
+                power_manager.set_brightness(
+                    settings.brightness,
+                    now,
+                    &mut display,
+                    &mut backlight,
+                    &mut touch_screen,
+                )?;
+                screen.set_style(WidgetStyle::from_theme(&theme));
+            }
+            if event == Some(SettingsEvent::Done) {
+                settings_screen = None;
+                if let Some(current) = &mut challenge {
+                    current.resume();
+                }
+                runner.delay(now - paused_at);
+                enemies.delay(now - paused_at);
+                save_settings(&mut settings_store, &settings);
+                if same_round(&round_settings, &settings) {
+                    redraw_round(
+                        &painter,
+                        &mut runner,
+                        &mut enemies,
+                        &theme,
+                        &dpad,
+                        &mut display,
+                    );
+                } else {
+                    if settings.challenge != round_settings.challenge {
+                        challenge = settings
+                            .challenge_mode()
+                            .map(|mode| Challenge::new(mode, rand::random(), &clock));
+                    }
+                    round_settings = settings;
+                    let (width, height) = maze_size(&challenge, settings.difficulty);
+                    let seed = challenge.as_ref().map(|challenge| challenge.seed());
+                    (painter, runner, enemies) =
+                        new_round(width, height, seed, &settings, &theme, &dpad, &mut display);
+                }
+            } else if screen.update(&mut display) {
+                display.flush();
+            }
+            if touch.is_none() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            continue;
+        }
+        // A long press around the maze opens the settings
+        if let Some(Gesture::LongPress(at)) = gesture {
+            let on_dpad = PLAY_MODE == PlayMode::Runner && dpad.hit(at).is_some();
+            let playing = matches!(PLAY_MODE, PlayMode::Tap | PlayMode::Runner);
+            if playing && !painter.touch_area().contains(at) && !on_dpad {
+                let area = Rectangle::new(
+                    Point::zero(),
+                    Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32),
+                );
+                let mut screen =
+                    SettingsScreen::new(settings, area, WidgetStyle::from_theme(&theme));
+                screen.draw(&mut display);
+                display.flush();
+                settings_screen = Some(screen);
+                opening_settings = true;
+                paused_at = now;
+                if let Some(current) = &mut challenge {
+                    current.pause();
+                }
+                continue;
+            }
+        }
+        let goal = painter.goal;
+        let style = theme.path_style();
+        let mut solved = false;
+        match PLAY_MODE {
+            PlayMode::Tap => {
+                if let Some(at) = point {
+                    info!("state: {:?}", at);
+                    if touch_started {
+                        outside = !painter.touch_area().contains(at);
+                        undo = painter
+                            .point_to_cell(at.x, at.y)
+                            .filter(|&cell| painter.maze[cell].visited);
+                    }
+                    if !outside && painter.on_click(at.x, at.y, style, &mut display) {
+                        solved = painter.is_solved();
+                    }
+                    display.flush();
+                }
+                // A tap around the maze switches to the next theme
+                if let Some(Gesture::Tap(at)) = gesture {
+                    if !painter.touch_area().contains(at) {
+                        switch_theme(
+                            &mut settings,
+                            &mut theme,
+                            &mut painter,
+                            &mut runner,
+                            &mut enemies,
+                            &dpad,
+                            &mut display,
+                        );
+                        save_settings(&mut settings_store, &settings);
+                    } else if let Some((x, y)) = undo.take() {
+                        if painter.unvisit(x, y, &mut display) {
+                            display.flush();
+                        }
+                    }
+                }
+            }
+            PlayMode::Runner => {
+                let direction = match gesture {
+                    Some(Gesture::Swipe(direction)) => Some(direction),
+                    Some(Gesture::Tap(point)) => {
+                        let direction = dpad.hit(point);
+                        if direction.is_none() && !painter.area().contains(point) {
+                            switch_theme(
+                                &mut settings,
+                                &mut theme,
+                                &mut painter,
+                                &mut runner,
+                                &mut enemies,
+                                &dpad,
+                                &mut display,
+                            );
+                            save_settings(&mut settings_store, &settings);
+                        }
+                        direction
+                    }
+                    Some(Gesture::LongPress(_)) | None => None,
+                };
+                if let Some(direction) = direction {
+                    if !runner.start_move(&painter.maze, direction, goal, now) {
+                        info!("Can't move {:?} from {:?}", direction, runner.position());
+                    }
+                }
+                if runner.is_moving() {
+                    solved =
+                        runner.update(&mut painter, now, &mut display) && runner.position() == goal;
+                }
+                if enemies.tick(&painter, runner.position(), now, &mut display) {
+                    info!("Caught at {:?}", runner.position());
+                    // Start over on the same maze, or a new one outside of a challenge
+                    let (width, height) = (painter.maze.width, painter.maze.height);
+                    let seed = challenge.as_ref().map(|challenge| challenge.seed());
+                    (painter, runner, enemies) =
+                        new_round(width, height, seed, &settings, &theme, &dpad, &mut display);
+                    continue;
+                }
+                display.flush();
+            }
+            PlayMode::Race => {
+                let Some(current) = &mut race else {
+                    continue;
+                };
+                match race_won_at {
+                    None if !touches.is_empty() => {
+                        if current.on_touches(&touches, style, &mut display).is_some() {
+                            current.draw_winner(
+                                PrimitiveStyle::with_stroke(Rgb565::GREEN, 4),
+                                &mut display,
+                            );
+                            race_won_at = Some(now);
+                        }
+                        display.flush();
+                    }
+                    // Start a new race with a touch after a while
+                    Some(won_at) if touch.is_some() && now - won_at > RACE_RESTART_DELAY => {
+                        *current = new_race(&mut display);
+                        race_won_at = None;
+                    }
+                    _ => {}
+                }
+            }
+            PlayMode::Floors => {
+                if let (Some(current), Some(point)) = (&mut floors, touch) {
+                    if current.on_click(point.x as i32, point.y as i32, &mut display) {
+                        solved = current.is_solved();
+                        display.flush();
+                    }
+                }
+            }
+        }
+        if solved && PLAY_MODE == PlayMode::Floors {
+            info!("Reached the top floor");
+            floors = Some(new_floors(&mut display));
+            continue;
+        }
+        if solved {
+            let coins = painter.maze.collected_coins();
+            info!("Reached the goal with {} coins", coins);
+            // A challenge goes on with the next maze right away
+            if challenge.is_none() {
+                let message = if settings.items {
+                    format!("Solved!\n{} coins", coins)
+                } else {
+                    "Solved!".to_string()
+                };
+                show_message(&message, painter.area(), &theme, &mut display);
+            }
+        }
+
+        if let Some(current) = &mut challenge {
+            let next = match (solved, current.tick()) {
+                (true, ChallengeState::Running) => {
+                    let time = current.solve();
+                    info!("Level {} solved in {:?}", current.level(), time);
+                    match current.mode() {
+                        ChallengeMode::Survival { .. } => {
+                            current.next_level();
+                            Some(current.seed())
+                        }
+                        ChallengeMode::TimeAttack { .. } => {
+                            let rank = time.and_then(|t| leaderboard.record(current.seed(), t));
+                            info!(
+                                "Rank {:?}, best times for seed {}: {:?}",
+                                rank,
+                                current.seed(),
+                                leaderboard.times(current.seed())
+                            );
+                            // Run the same maze again to beat the record
+                            *current = Challenge::new(current.mode(), current.seed(), &clock);
+                            Some(current.seed())
+                        }
+                        ChallengeMode::Countdown { .. } => {
+                            *current = Challenge::new(current.mode(), rand::random(), &clock);
+                            Some(current.seed())
+                        }
+                    }
+                }
+                (_, ChallengeState::TimedOut) => {
+                    info!("Time is up at level {}", current.level());
+                    *current = Challenge::new(current.mode(), rand::random(), &clock);
+                    Some(current.seed())
+                }
+                _ => None,
+            };
+            if let Some(seed) = next {
+                let (width, height) = current.maze_size();
+                (painter, runner, enemies) = new_round(
+                    width,
+                    height,
+                    Some(seed),
+                    &settings,
+                    &theme,
+                    &dpad,
+                    &mut display,
+                );
+                info!(
+                    "Level {}, time left {:?}",
+                    current.level(),
+                    current.remaining()
+                );
+            }
+        }
+
+        if touch.is_none() {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// Whether the maze made with `old` is still right for `new`, the other settings apply to it
+/// right away
+fn same_round(old: &Settings, new: &Settings) -> bool {
+    let round = |settings: &Settings| {
+        (
+            settings.difficulty,
+            settings.algorithm,
+            settings.challenge,
+            settings.enemies,
+            settings.fog,
+            settings.shape,
+            settings.items,
+        )
+    };
+    round(old) == round(new)
+}
+
+fn default_maze_width() -> usize {
+    match PLAY_MODE {
+        PlayMode::Tap => MAZE_WIDTH,
+        PlayMode::Runner => RUNNER_MAZE_WIDTH,
+        PlayMode::Race => RACE_MAZE_WIDTH,
+        PlayMode::Floors => MAZE_WIDTH,
+    }
+}
+
+/// The maze of the challenge, or as many cells of the difficulty as fit on the screen
+fn maze_size<K: Clock>(challenge: &Option<Challenge<K>>, difficulty: Difficulty) -> (usize, usize) {
+    match challenge {
+        Some(challenge) => challenge.maze_size(),
+        None => {
+            let cell_size = difficulty.cell_size() as usize;
+            (
+                default_maze_width() * CELL_SIZE / cell_size,
+                MAZE_HEIGHT * CELL_SIZE / cell_size,
+            )
+        }
+    }
+}
+
+/// The cell size of the difficulty, smaller if the maze wouldn't fit the screen with it
+fn cell_size(difficulty: Difficulty, width: usize, height: usize) -> Size {
+    let size = (difficulty.cell_size() as usize)
+        .min(default_maze_width() * CELL_SIZE / width.max(1))
+        .min(MAZE_HEIGHT * CELL_SIZE / height.max(1)) as u32;
+    Size::new(size, size)
+}
+
+/// Generate a new maze with `FLOORS` floors and show the bottom one
+fn new_floors(display: &mut HX8369) -> LayeredPainter<Rgb565> {
+    let mut rng = StdRng::from_entropy();
+    let goal = (MAZE_WIDTH - 1, MAZE_HEIGHT - 1, FLOORS - 1);
+    let maze = loop {
+        let mut maze = LayeredMaze::new(MAZE_WIDTH, MAZE_HEIGHT, FLOORS);
+        maze.generate_with_rng(STAIRS, &mut rng);
+        match maze.solve((0, 0, 0), goal) {
+            Some(path) => {
+                info!("The top floor is {} cells away", path.len() - 1);
+                break maze;
+            }
+            None => info!("The top floor can't be reached, generating again"),
+        }
+    };
+    let mut painter = LayeredPainter::new(
+        maze,
+        PrimitiveStyleBuilder::new()
+            .fill_color(Rgb565::YELLOW)
+            .stroke_color(Rgb565::WHITE)
+            .stroke_width(1)
+            .build(),
+        PathStyle::solid(Rgb565::GREEN, 3),
+        Rgb565::BLACK,
+        Size::new(CELL_SIZE as u32, CELL_SIZE as u32),
+        Point::new(X_OFFSET as i32, Y_OFFSET as i32),
+    );
+    painter.show_floor(0, display);
+    display.flush();
+    painter
+}
 
+/// Start a race on a new maze
+fn new_race(display: &mut HX8369) -> Race<Rgb565> {
     let style = PrimitiveStyleBuilder::new()
         .fill_color(Rgb565::YELLOW)
         .stroke_color(Rgb565::WHITE)
         .stroke_width(1)
         .build();
+    let race = Race::new(
+        (RACE_MAZE_WIDTH, MAZE_HEIGHT),
+        rand::random(),
+        style,
+        Size::new(CELL_SIZE as u32, CELL_SIZE as u32),
+        Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32),
+    );
+    display.clear(Rgb565::BLACK).ok();
+    race.draw(display).ok();
+    display.flush();
+    race
+}
 
-    let offset = Point {
-        x: X_OFFSET as i32,
-        y: Y_OFFSET as i32,
+/// A new maze for the attract mode
+fn new_attract(theme: &Theme<Rgb565>, now: Instant) -> Attract<Rgb565> {
+    let mut maze = maze::Maze::new(MAZE_WIDTH, MAZE_HEIGHT);
+    maze.generate(0, 0);
+    let painter = MazePainter::new(
+        maze,
+        theme.wall_style(),
+        Size::new(CELL_SIZE as u32, CELL_SIZE as u32),
+        Point::new(X_OFFSET as i32, Y_OFFSET as i32),
+    )
+    .with_theme(theme);
+    let style = SearchStyle {
+        frontier: Rgb565::CSS_DARK_SLATE_BLUE,
+        visited: Rgb565::CSS_NAVY,
+        backtracked: Rgb565::CSS_DARK_RED,
+        path: PrimitiveStyle::with_stroke(theme.path.colors[0], theme.path.width),
     };
-    let cell_size = Size::new(CELL_SIZE as u32, CELL_SIZE as u32);
+    Attract::new(painter, style, theme.background, ATTRACT_STEP, now)
+}
 
-    let mut painter = MazePainter::new(maze, style, cell_size, offset);
+/// A message in a box over the middle of the area, in the HUD colors of the theme
+fn show_message(text: &str, over: Rectangle, theme: &Theme<Rgb565>, display: &mut HX8369) {
+    let style = TextStyle::large(theme.hud)
+        .background(theme.hud_background)
+        .align(Alignment::Center, VerticalAlignment::Middle);
+    let size = style.measure(text, over.size.width / 2) + Size::new(32, 24);
+    let area = Rectangle::with_center(over.center(), size);
+    Label::new(text, area, style).draw(display).ok();
+    area.into_styled(PrimitiveStyle::with_stroke(theme.hud, 2))
+        .draw(display)
+        .ok();
+    display.flush();
+}
 
-    painter.draw(&mut display).ok();
+/// Go to the next built-in theme and draw everything again with it
+fn switch_theme(
+    settings: &mut Settings,
+    theme: &mut Theme<Rgb565>,
+    painter: &mut MazePainter<Rgb565>,
+    runner: &mut Runner<Rgb565>,
+    enemies: &mut Enemies<Rgb565>,
+    dpad: &DPad<Rgb565>,
+    display: &mut HX8369,
+) {
+    settings.theme = (settings.theme + 1) % Theme::ALL.len();
+    set_theme(Theme::ALL[settings.theme], theme, painter, runner, enemies);
+    redraw_round(painter, runner, enemies, theme, dpad, display);
+}
 
-    display.flush();
+fn set_theme(
+    new: Theme<Rgb565>,
+    theme: &mut Theme<Rgb565>,
+    painter: &mut MazePainter<Rgb565>,
+    runner: &mut Runner<Rgb565>,
+    enemies: &mut Enemies<Rgb565>,
+) {
+    *theme = new;
+    info!("Theme {}", theme.name);
+    painter.set_theme(theme);
+    runner.set_background(theme.background);
+    enemies.set_background(theme.background);
+}
 
-    let style = PrimitiveStyleBuilder::new()
-        .stroke_color(Rgb565::GREEN)
-        .stroke_width(3)
-        .build();
+/// Apply the game settings that don't need a new maze, the round is drawn again
+/// once the settings are closed
+fn apply_settings(
+    settings: &Settings,
+    theme: &mut Theme<Rgb565>,
+    painter: &mut MazePainter<Rgb565>,
+    runner: &mut Runner<Rgb565>,
+    enemies: &mut Enemies<Rgb565>,
+) {
+    if Theme::ALL[settings.theme] != *theme {
+        set_theme(Theme::ALL[settings.theme], theme, painter, runner, enemies);
+    }
+    painter.click_approximation = settings.click_approximation;
+}
 
-    loop {
-        let touch = touch_screen.read_touch()?;
-        if let Some(point) = touch {
-            info!("state: {:?}", point);
-            painter.on_click(point.x as i32, point.y as i32, style, &mut display);
+/// Keep the settings for the next start, unless there is no storage
+fn save_settings(store: &mut Option<SettingsStore>, settings: &Settings) {
+    if let Some(store) = store {
+        if let Err(err) = store.save(settings) {
+            info!("Can't save the settings: {:?}", err);
+        }
+    }
+}
+
+/// Draw the round again as it was, e.g. after the attract mode
+fn redraw_round(
+    painter: &MazePainter<Rgb565>,
+    runner: &mut Runner<Rgb565>,
+    enemies: &mut Enemies<Rgb565>,
+    theme: &Theme<Rgb565>,
+    dpad: &DPad<Rgb565>,
+    display: &mut HX8369,
+) {
+    display.clear(theme.background).ok();
+    painter.draw(display).ok();
+    match PLAY_MODE {
+        PlayMode::Runner => {
+            runner.draw(painter, display);
+            dpad.draw(display).ok();
+            enemies.draw(painter, display);
+        }
+        _ => painter.draw_path(&theme.path_style(), display),
+    }
+    display.flush();
+}
+
+/// Generate the maze while drawing every carved passage
+fn animate_generation(
+    maze: maze::Maze,
+    rng: &mut StdRng,
+    delay: Duration,
+    cell_size: Size,
+    display: &mut HX8369,
+) -> maze::Maze {
+    let mut painter = MazePainter::new(
+        maze,
+        PrimitiveStyle::with_stroke(Rgb565::WHITE, 1),
+        cell_size,
+        Point::new(X_OFFSET as i32, Y_OFFSET as i32),
+    )
+    .without_markers();
+    display.clear(Rgb565::BLACK).ok();
+    painter.draw(display).ok();
+    display.flush();
+    let mut generator = Generator::new(0, 0, rng);
+    while let Some(event) = generator.step(&mut painter.maze) {
+        if let GenerationEvent::Carve { .. } = event {
+            painter.draw_generation_event(event, Rgb565::BLACK, display);
             display.flush();
-        } else {
-            thread::sleep(Duration::from_millis(10));
+            thread::sleep(delay);
+        }
+    }
+    painter.maze
+}
+
+/// Generate a new maze and draw the whole screen for it
+fn new_round(
+    width: usize,
+    height: usize,
+    seed: Option<u64>,
+    settings: &Settings,
+    theme: &Theme<Rgb565>,
+    dpad: &DPad<Rgb565>,
+    display: &mut HX8369,
+) -> (MazePainter<Rgb565>, Runner<Rgb565>, Enemies<Rgb565>) {
+    let mut maze = maze::Maze::new(width, height);
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    if let Some(shape) = settings.mask_shape() {
+        maze.apply_mask(&shape.mask(width, height));
+    }
+    let cell_size = cell_size(settings.difficulty, width, height);
+    match (settings.algorithm, GENERATION_DELAY) {
+        (Algorithm::Weave, _) => weave::generate_weave(&mut maze, WEAVE_DENSITY, &mut rng),
+        (Algorithm::Backtracker, Some(delay)) => {
+            maze = animate_generation(maze, &mut rng, delay, cell_size, display)
+        }
+        (Algorithm::Backtracker, None) => maze.generate_with_rng(0, 0, &mut rng),
+    }
+    // The corners may not be in the shape
+    let (start, goal) = if LONGEST_PATH || settings.mask_shape().is_some() {
+        solver::longest_path(&maze)
+    } else {
+        ((0, 0), (width - 1, height - 1))
+    };
+    if settings.items {
+        items::place_items(&mut maze, &ITEMS, start, goal, &mut rng);
+    }
+
+    let offset = Point {
+        x: X_OFFSET as i32,
+        y: Y_OFFSET as i32,
+    };
+    let mut painter = MazePainter::new(maze, theme.wall_style(), cell_size, offset)
+        .with_theme(theme)
+        .with_endpoints(start, goal)
+        .with_item_style(ItemStyle {
+            coin: Rgb565::YELLOW,
+            keys: [
+                Rgb565::RED,
+                Rgb565::BLUE,
+                Rgb565::MAGENTA,
+                Rgb565::CSS_ORANGE,
+            ],
+            teleporter: Rgb565::CYAN,
+            one_way: Rgb565::CSS_GRAY,
+        });
+    painter.click_approximation = settings.click_approximation;
+    if BLOCKS {
+        painter = painter.with_blocks();
+    }
+    if let Some(visibility) = settings.visibility() {
+        painter = painter.with_fog(visibility);
+    }
+
+    display.clear(theme.background).ok();
+    painter.draw(display).ok();
+
+    let mut runner = Runner::new(
+        start,
+        PrimitiveStyle::with_fill(Rgb565::CYAN),
+        theme.background,
+    );
+    let mut enemies = Enemies::new(theme.background, rng.gen());
+    if PLAY_MODE == PlayMode::Runner {
+        runner.draw(&painter, display);
+        dpad.draw(display).ok();
+        let now = Instant::now();
+        for behavior in settings.enemy_behaviors() {
+            let position = enemies.far_cell(&painter.maze, start, ENEMY_MIN_DISTANCE);
+            let style = PrimitiveStyle::with_fill(match behavior {
+                Behavior::RandomWalker => Rgb565::MAGENTA,
+                Behavior::WallFollower => Rgb565::CSS_ORANGE,
+                Behavior::Chaser => Rgb565::RED,
+            });
+            enemies.add(Enemy::new(*behavior, position, ENEMY_PERIOD, style, now));
         }
+        enemies.draw(&painter, display);
     }
+
+    display.flush();
+    (painter, runner, enemies)
 }
@@ -0,0 +1,235 @@
+/// Shapes for masked mazes, one bit per maze cell. Only the cells set in the mask are part
+/// of the maze. A mask is made from a 1-bit bitmap, ASCII art, or by drawing on it
+/// with `embedded-graphics` since it is a `DrawTarget` where each pixel is a cell.
+use embedded_graphics::{
+    geometry::Dimensions,
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, OriginDimensions, Point, Primitive, Size},
+    primitives::{Circle, PrimitiveStyle},
+    text::{Baseline, Text},
+    Drawable, Pixel,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<bool>,
+}
+
+impl Mask {
+    /// An empty mask, draw on it to add cells
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![false; width * height],
+        }
+    }
+
+    /// A 1-bit bitmap, most significant bit first, each row padded to a whole byte,
+    /// the same layout as `ImageRaw<BinaryColor>`
+    pub fn from_bitmap(data: &[u8], width: usize, height: usize) -> Self {
+        let mut mask = Self::new(width, height);
+        let stride = width.div_ceil(8);
+        for y in 0..height {
+            for x in 0..width {
+                let byte = data.get(y * stride + x / 8).copied().unwrap_or(0);
+                mask.set(x, y, byte & (0x80 >> (x % 8)) != 0);
+            }
+        }
+        mask
+    }
+
+    /// ASCII art, one line per row, spaces and dots are outside of the maze
+    pub fn from_ascii(art: &str) -> Self {
+        let lines: Vec<&str> = art.lines().collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut mask = Self::new(width, lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                mask.set(x, y, c != ' ' && c != '.');
+            }
+        }
+        mask
+    }
+
+    /// The text centered in the mask, each font pixel scaled up to as many cells as fit
+    pub fn from_text(text: &str, width: usize, height: usize) -> Self {
+        let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let text = Text::with_baseline(text, Point::zero(), style, Baseline::Top);
+        let size = text.bounding_box().size;
+        let mut glyphs = Self::new(size.width as usize, size.height as usize);
+        text.draw(&mut glyphs).ok();
+
+        let scale = (width / glyphs.width.max(1))
+            .min(height / glyphs.height.max(1))
+            .max(1);
+        let left = width.saturating_sub(glyphs.width * scale) / 2;
+        let top = height.saturating_sub(glyphs.height * scale) / 2;
+        let mut mask = Self::new(width, height);
+        for y in 0..height.saturating_sub(top) {
+            for x in 0..width.saturating_sub(left) {
+                mask.set(left + x, top + y, glyphs.get(x / scale, y / scale));
+            }
+        }
+        mask
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, active: bool) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = active;
+        }
+    }
+
+    /// This mask in the middle of a bigger one, cut if it doesn't fit
+    fn centered(&self, width: usize, height: usize) -> Self {
+        let left = width.saturating_sub(self.width) / 2;
+        let top = height.saturating_sub(self.height) / 2;
+        let mut mask = Self::new(width, height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                mask.set(left + x, top + y, self.get(x, y));
+            }
+        }
+        mask
+    }
+}
+
+/// Shape of the maze, instead of the whole rectangle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MaskShape {
+    /// ASCII art, spaces and dots are outside of the maze
+    Ascii(&'static str),
+    /// 1-bit bitmap with its width and height in cells, e.g. from `include_bytes!`,
+    /// in the layout of `ImageRaw<BinaryColor>`
+    Bitmap(&'static [u8], usize, usize),
+    /// Text in the built-in font, one font pixel per cell
+    Text(&'static str),
+    /// The largest circle fitting in the maze
+    Circle,
+}
+
+impl MaskShape {
+    /// The mask for a maze of the given size, pictures are centered in it
+    pub fn mask(self, width: usize, height: usize) -> Mask {
+        match self {
+            MaskShape::Ascii(art) => Mask::from_ascii(art).centered(width, height),
+            MaskShape::Bitmap(data, bitmap_width, bitmap_height) => {
+                Mask::from_bitmap(data, bitmap_width, bitmap_height).centered(width, height)
+            }
+            MaskShape::Text(text) => Mask::from_text(text, width, height),
+            MaskShape::Circle => {
+                let mut mask = Mask::new(width, height);
+                let diameter = width.min(height) as u32;
+                Circle::with_center(Point::new(width as i32 / 2, height as i32 / 2), diameter)
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(&mut mask)
+                    .ok();
+                mask
+            }
+        }
+    }
+}
+
+impl OriginDimensions for Mask {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl DrawTarget for Mask {
+    type Color = BinaryColor;
+
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.set(point.x as usize, point.y as usize, color.is_on());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{maze::Maze, solver};
+
+    fn cells(mask: &Mask) -> Vec<(usize, usize)> {
+        (0..mask.height)
+            .flat_map(|y| (0..mask.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| mask.get(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn bitmap_rows_are_padded_to_a_byte() {
+        // 10 cells wide, 2 bytes per row, the padding bits are not cells
+        let data = [0b1000_0000, 0b0111_1111, 0b0000_0001, 0b1000_0000];
+        let mask = Mask::from_bitmap(&data, 10, 3);
+        assert_eq!(cells(&mask), [(0, 0), (9, 0), (7, 1), (8, 1)]);
+        assert!(!mask.get(10, 0));
+    }
+
+    #[test]
+    fn ascii_lines_are_rows() {
+        let mask = Mask::from_ascii("#.#\n #\n###x");
+        assert_eq!((mask.width, mask.height), (4, 3));
+        assert_eq!(
+            cells(&mask),
+            [(0, 0), (2, 0), (1, 1), (0, 2), (1, 2), (2, 2), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn text_is_scaled_and_centered() {
+        let glyph = Mask::from_text("I", 6, 10);
+        let glyph_cells = cells(&glyph);
+        assert!(!glyph_cells.is_empty());
+        // Twice as big, the height is the limit
+        let mask = Mask::from_text("I", 30, 20);
+        let scaled = cells(&mask);
+        assert_eq!(scaled.len(), glyph_cells.len() * 4);
+        for (x, y) in glyph_cells {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                assert!(mask.get(9 + 2 * x + dx, 2 * y + dy));
+            }
+        }
+        // Too small for the text, only the first letter fits
+        assert_eq!(Mask::from_text("Hello", 6, 10), Mask::from_text("H", 6, 10));
+    }
+
+    #[test]
+    fn maze_stays_in_the_mask() {
+        let mask = Mask::from_ascii("..##..\n######\n######\n..##..");
+        let mut maze = Maze::new(6, 4);
+        maze.apply_mask(&mask);
+        let start = maze.first_active_cell().unwrap();
+        maze.generate_seeded(start.0, start.1, 2);
+        for y in 0..4 {
+            for x in 0..6 {
+                let open = !maze.open_neighbors(x, y).is_empty();
+                assert_eq!(open, mask.get(x, y), "({}, {})", x, y);
+                for (nx, ny) in maze.open_neighbors(x, y) {
+                    assert!(mask.get(nx, ny));
+                }
+            }
+        }
+        assert_eq!(solver::reachable(&maze, start, 0).len(), cells(&mask).len());
+    }
+}
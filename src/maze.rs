@@ -1,7 +1,13 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    collections::VecDeque,
+    ops::{Index, IndexMut},
+};
 
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
+use crate::{generator::Generator, mask::Mask};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Direction {
     North,
     South,
@@ -9,12 +15,101 @@ pub enum Direction {
     West,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// The direction after turning left, with north up
+    pub fn left(self) -> Direction {
+        match self {
+            Direction::North => Direction::West,
+            Direction::West => Direction::South,
+            Direction::South => Direction::East,
+            Direction::East => Direction::North,
+        }
+    }
+
+    pub fn right(self) -> Direction {
+        self.left().opposite()
+    }
+
+    /// Offset of the neighbor cell in this direction
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+}
+
+/// Number of different keys, the collected keys are kept in a bit mask
+pub const MAX_KEYS: u8 = 4;
+
+/// Something placed in a cell
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellContent {
+    Empty,
+    Coin,
+    /// Opens the doors with the same number
+    Key(u8),
+    /// Can only be entered with the key of the same number
+    Door(u8),
+    /// Entering the cell moves the player to the other end
+    Teleporter {
+        to: (usize, usize),
+    },
+}
+
 pub struct MazeCell {
     pub north: bool,
     pub south: bool,
     pub east: bool,
     pub west: bool,
     pub visited: bool,
+    pub content: CellContent,
+    /// The passage in this direction can only be walked out of this cell, not into it
+    pub one_way: Option<Direction>,
+    /// Stairs to the same cell on the floor above/below, only used by `LayeredMaze`
+    pub up: bool,
+    pub down: bool,
+    /// Cells outside of the mask are not part of the maze
+    pub active: bool,
+    /// Two corridors crossing in the cell of a weave maze, it's never a stop on the way
+    pub crossing: Option<Crossing>,
+}
+
+impl MazeCell {
+    /// Whether there is a passage in the direction, i.e. no wall
+    pub fn is_open(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::North => self.north,
+            Direction::South => self.south,
+            Direction::East => self.east,
+            Direction::West => self.west,
+        }
+    }
+}
+
+/// Which of the two corridors crossing in a cell goes over the other one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Crossing {
+    NorthSouthOver,
+    EastWestOver,
 }
 
 pub struct Maze {
@@ -33,6 +128,12 @@ impl Maze {
                 east: false,
                 west: false,
                 visited: false,
+                content: CellContent::Empty,
+                one_way: None,
+                up: false,
+                down: false,
+                active: true,
+                crossing: None,
             });
         }
         Maze {
@@ -42,6 +143,86 @@ impl Maze {
         }
     }
 
+    /// The cell in the direction if there is a passage to it,
+    /// a crossing is passed straight to the cell on the other side
+    pub fn neighbor(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        let next = self.adjacent(x, y, direction)?;
+        if self[next].crossing.is_some() {
+            return self.adjacent(next.0, next.1, direction);
+        }
+        Some(next)
+    }
+
+    /// The cell right next to this one in the direction if there is a passage to it
+    pub fn adjacent(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        if !self[(x, y)].is_open(direction) {
+            return None;
+        }
+        let (dx, dy) = direction.offset();
+        let nx = x as i32 + dx;
+        let ny = y as i32 + dy;
+        if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+            return None;
+        }
+        Some((nx as usize, ny as usize))
+    }
+
+    /// The cell in the direction if the player can move there, i.e. there is a passage,
+    /// it's not a one-way passage in the opposite direction, and the keys open the door if any
+    pub fn can_move(
+        &self,
+        x: usize,
+        y: usize,
+        direction: Direction,
+        keys: u8,
+    ) -> Option<(usize, usize)> {
+        let (nx, ny) = self.neighbor(x, y, direction)?;
+        let next = &self[(nx, ny)];
+        if next.one_way == Some(direction.opposite()) {
+            return None;
+        }
+        if let CellContent::Door(key) = next.content {
+            if keys & (1 << key) == 0 {
+                return None;
+            }
+        }
+        Some((nx, ny))
+    }
+
+    /// Bit mask of the keys in the visited cells
+    pub fn collected_keys(&self) -> u8 {
+        self.cells
+            .iter()
+            .filter(|cell| cell.visited)
+            .fold(0, |keys, cell| match cell.content {
+                CellContent::Key(key) => keys | (1 << key),
+                _ => keys,
+            })
+    }
+
+    pub fn collected_coins(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| cell.visited && cell.content == CellContent::Coin)
+            .count()
+    }
+
+    /// Directions with a passage out of the cell
+    pub fn open_directions(&self, x: usize, y: usize) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|d| self.neighbor(x, y, *d).is_some())
+            .collect()
+    }
+
+    /// The cells there is a passage to
+    pub fn open_neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|d| self.neighbor(x, y, d))
+            .collect()
+    }
+
     fn get_cell_neighbors_when(
         &self,
         x: usize,
@@ -72,7 +253,7 @@ impl Maze {
         self.get_cell_neighbors_when(x, y, |cell| cell.visited)
     }
 
-    fn get_cell_unvisited_neighbors_with_visited_neighbors(
+    pub fn get_cell_unvisited_neighbors_with_visited_neighbors(
         &self,
         x: usize,
         y: usize,
@@ -86,60 +267,87 @@ impl Maze {
         neighbors
     }
 
-    pub fn generate(&mut self, x: usize, y: usize) {
-        let mut stack = Vec::with_capacity(self.width * self.height);
-        stack.push((x, y));
-
-        // Pop the current cell
-        while let Some((x, y)) = stack.pop() {
-            // Mark the current cell as visited
-            self[(x, y)].visited = true;
-            // Get the unvisited neighbors with visited neighbors
-            let mut neighbors = self.get_cell_unvisited_neighbors_with_visited_neighbors(x, y);
-            // If there are no unvisited neighbors with visited neighbors, continue
-            if neighbors.is_empty() {
-                continue;
+    /// Only keep the cells set in the mask, call it before generating
+    pub fn apply_mask(&mut self, mask: &Mask) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self[(x, y)].active = mask.get(x, y);
             }
-            // Push the current cell back onto the stack
-            stack.push((x, y));
-
-            // Choose a random unvisited neighbor and remove walls between the current cell and the neighbor
-            let (nx, ny) = neighbors.remove(rand::thread_rng().gen_range(0..neighbors.len()));
-            let direction = if nx > x {
-                Direction::East
-            } else if nx < x {
-                Direction::West
-            } else if ny > y {
-                Direction::South
-            } else {
-                Direction::North
-            };
-            match direction {
-                Direction::North => {
-                    self[(x, y)].north = true;
-                    self[(nx, ny)].south = true;
-                }
-                Direction::East => {
-                    self[(x, y)].east = true;
-                    self[(nx, ny)].west = true;
-                }
-                Direction::West => {
-                    self[(x, y)].west = true;
-                    self[(nx, ny)].east = true;
+        }
+    }
+
+    pub fn first_active_cell(&self) -> Option<(usize, usize)> {
+        let index = self.cells.iter().position(|cell| cell.active)?;
+        Some((index % self.width, index / self.width))
+    }
+
+    /// Shortest way from the visited cells to an unvisited active cell, through any cells.
+    /// The first cell is visited, the last one is the unvisited active cell.
+    pub fn bridge_to_unvisited(&self) -> Option<Vec<(usize, usize)>> {
+        let mut previous: Vec<Option<(usize, usize)>> = vec![None; self.cells.len()];
+        let mut seen: Vec<bool> = self
+            .cells
+            .iter()
+            .map(|cell| cell.visited && cell.active)
+            .collect();
+        let mut queue: VecDeque<(usize, usize)> = (0..self.cells.len())
+            .filter(|&i| seen[i])
+            .map(|i| (i % self.width, i / self.width))
+            .collect();
+        while let Some((x, y)) = queue.pop_front() {
+            if self[(x, y)].active && !self[(x, y)].visited {
+                let mut path = vec![(x, y)];
+                let mut current = (x, y);
+                while let Some(prev) = previous[current.1 * self.width + current.0] {
+                    path.push(prev);
+                    current = prev;
                 }
-                Direction::South => {
-                    self[(x, y)].south = true;
-                    self[(nx, ny)].north = true;
+                path.reverse();
+                return Some(path);
+            }
+            for (nx, ny) in self.get_cell_neighbors_when(x, y, |_| true) {
+                if !seen[ny * self.width + nx] {
+                    seen[ny * self.width + nx] = true;
+                    previous[ny * self.width + nx] = Some((x, y));
+                    queue.push_back((nx, ny));
                 }
             }
-            // Push the neighbor onto the stack as the current cell for the next iteration
-            stack.push((nx, ny));
         }
-        // Clear all visited flags
-        for cell in &mut self.cells {
-            cell.visited = false;
+        None
+    }
+
+    /// Remove the wall between two cells next to each other
+    pub fn carve(&mut self, (x, y): (usize, usize), (nx, ny): (usize, usize)) {
+        if nx > x {
+            self[(x, y)].east = true;
+            self[(nx, ny)].west = true;
+        } else if nx < x {
+            self[(x, y)].west = true;
+            self[(nx, ny)].east = true;
+        } else if ny > y {
+            self[(x, y)].south = true;
+            self[(nx, ny)].north = true;
+        } else {
+            self[(x, y)].north = true;
+            self[(nx, ny)].south = true;
         }
     }
+
+    pub fn generate(&mut self, x: usize, y: usize) {
+        self.generate_with_rng(x, y, &mut rand::thread_rng());
+    }
+
+    /// Generate the maze from a seed, the same seed always gives the same maze
+    pub fn generate_seeded(&mut self, x: usize, y: usize, seed: u64) {
+        self.generate_with_rng(x, y, &mut StdRng::seed_from_u64(seed));
+    }
+
+    /// Carve the maze from `(x, y)`, or from the first active cell if it is outside of the mask.
+    /// Parts of the mask that don't touch each other get joined by corridors of new cells.
+    pub fn generate_with_rng<R: Rng>(&mut self, x: usize, y: usize, rng: &mut R) {
+        let mut generator = Generator::new(x, y, rng);
+        while generator.step(self).is_some() {}
+    }
 }
 
 impl Index<(usize, usize)> for Maze {
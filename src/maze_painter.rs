@@ -2,34 +2,194 @@ use std::cmp::min;
 
 use embedded_graphics::{
     prelude::{PixelColor, Point, Size},
-    primitives::{Circle, Line, Primitive, PrimitiveStyle, StyledDrawable},
+    primitives::{
+        Circle, Line, Primitive, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StyledDrawable,
+        Triangle,
+    },
     transform::Transform,
     Drawable,
 };
 use log::info;
 
-use crate::maze::Maze;
+use crate::{
+    blocks::{BlockGrid, Tile},
+    fog::{Fog, Visibility},
+    generator::GenerationEvent,
+    maze::{CellContent, Crossing, Direction, Maze, MAX_KEYS},
+    path::PathStyle,
+    search::SearchEvent,
+    theme::Theme,
+    walls::wall_runs,
+};
 
-const CLICK_APPROXIMATION: u32 = 20;
+pub const CLICK_APPROXIMATION: u32 = 20;
 
 pub struct MazePainter<C: PixelColor> {
     pub maze: Maze,
     pub style: PrimitiveStyle<C>,
     pub cell_size: Size,
     pub offset: Point,
+    pub fog: Option<Fog>,
+    pub item_style: Option<ItemStyle<C>>,
+    /// Where the exploration starts, visited from the beginning
+    pub start: (usize, usize),
+    pub goal: (usize, usize),
+    /// Draw the start and goal markers
+    pub markers: bool,
+    pub start_style: PrimitiveStyle<C>,
+    pub goal_style: PrimitiveStyle<C>,
+    /// Fill of the visited cells
+    pub visited_fill: Option<C>,
+    pub rounded_corners: bool,
+    /// Needed to redraw single cells
+    pub background: Option<C>,
+    /// The path drawn through the visited cells when they are redrawn
+    pub path_style: Option<PathStyle<C>>,
+    /// How far from a cell, in pixels, a tap still reaches it
+    pub click_approximation: u32,
+    /// Steps from the start along the explored route, by cell
+    steps: Vec<Option<usize>>,
+    /// Draw the walls as tiles of a block grid instead of lines
+    pub blocks: Option<BlockGrid>,
+}
+
+/// Colors of the things placed in the cells
+#[derive(Copy, Clone, Debug)]
+pub struct ItemStyle<C: PixelColor> {
+    pub coin: C,
+    /// Key and door colors, by key number
+    pub keys: [C; MAX_KEYS as usize],
+    pub teleporter: C,
+    pub one_way: C,
+}
+
+/// Colors of a search being watched
+#[derive(Copy, Clone, Debug)]
+pub struct SearchStyle<C: PixelColor> {
+    pub frontier: C,
+    pub visited: C,
+    pub backtracked: C,
+    pub path: PrimitiveStyle<C>,
+}
+
+impl<C: PixelColor> ItemStyle<C> {
+    fn key_color(&self, key: u8) -> C {
+        self.keys[key as usize % self.keys.len()]
+    }
 }
 
 impl<C: PixelColor> MazePainter<C> {
+    /// The exploration starts at the top left corner and the goal is the bottom right one
     pub fn new(mut maze: Maze, style: PrimitiveStyle<C>, cell_size: Size, offset: Point) -> Self {
         maze[(0, 0)].visited = true;
+        let goal = (maze.width - 1, maze.height - 1);
+        let mut steps = vec![None; maze.cells.len()];
+        steps[0] = Some(0);
         Self {
             maze,
             style,
             cell_size,
             offset,
+            fog: None,
+            item_style: None,
+            start: (0, 0),
+            goal,
+            markers: true,
+            start_style: style,
+            goal_style: style,
+            visited_fill: None,
+            rounded_corners: false,
+            background: None,
+            path_style: None,
+            blocks: None,
+            click_approximation: CLICK_APPROXIMATION,
+            steps,
         }
     }
 
+    pub fn with_theme(mut self, theme: &Theme<C>) -> Self {
+        self.set_theme(theme);
+        self
+    }
+
+    /// Draw the maze as a block grid, the walls are tiles half a cell wide centered on the
+    /// grid lines so the cells stay where they are. Call it after generating the maze.
+    pub fn with_blocks(mut self) -> Self {
+        self.blocks = Some(BlockGrid::from_maze(&self.maze));
+        self
+    }
+
+    /// The part of the screen covered by a tile of the block grid
+    pub fn tile_area(&self, x: usize, y: usize) -> Rectangle {
+        let tile = self.cell_size / 2;
+        Rectangle::new(
+            Point::new(
+                (x as u32 * tile.width) as i32 - (tile.width / 2) as i32,
+                (y as u32 * tile.height) as i32 - (tile.height / 2) as i32,
+            ),
+            tile,
+        )
+        .translate(self.offset)
+    }
+
+    /// How far the walls reach across the grid lines, in total
+    fn wall_width(&self) -> u32 {
+        match self.blocks {
+            Some(_) => self.cell_size.width.min(self.cell_size.height) / 2,
+            None => self.style.stroke_width,
+        }
+    }
+
+    /// Take the colors and the wall style of the theme, draw everything again to see it
+    pub fn set_theme(&mut self, theme: &Theme<C>) {
+        self.style = PrimitiveStyleBuilder::from(&theme.wall_style())
+            .fill_color(theme.walls)
+            .build();
+        self.start_style = theme.marker_style(theme.start);
+        self.goal_style = theme.marker_style(theme.goal);
+        self.visited_fill = theme.visited;
+        self.rounded_corners = theme.rounded_corners;
+        self.background = Some(theme.background);
+        self.path_style = Some(theme.path_style());
+    }
+
+    /// The part of the screen covered by the maze
+    pub fn area(&self) -> Rectangle {
+        Rectangle::new(
+            self.offset,
+            Size::new(
+                self.maze.width as u32 * self.cell_size.width,
+                self.maze.height as u32 * self.cell_size.height,
+            ),
+        )
+    }
+
+    /// Where a touch still counts as on the maze, the area grown by the click approximation
+    pub fn touch_area(&self) -> Rectangle {
+        self.area().offset(self.click_approximation as i32)
+    }
+
+    /// Move the start and the goal, call it before `with_fog`
+    pub fn with_endpoints(mut self, start: (usize, usize), goal: (usize, usize)) -> Self {
+        self.maze[self.start].visited = false;
+        self.maze[start].visited = true;
+        self.steps[self.start.1 * self.maze.width + self.start.0] = None;
+        self.steps[start.1 * self.maze.width + start.0] = Some(0);
+        self.start = start;
+        self.goal = goal;
+        self
+    }
+
+    /// Don't draw the start and goal markers, e.g. when the goal is on another floor
+    pub fn without_markers(mut self) -> Self {
+        self.markers = false;
+        self
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.maze[self.goal].visited
+    }
+
     pub fn draw_marker<D>(
         &self,
         x: usize,
@@ -39,6 +199,17 @@ impl<C: PixelColor> MazePainter<C> {
     ) where
         D: embedded_graphics::prelude::DrawTarget<Color = C>,
     {
+        if self.blocks.is_some() {
+            let (tx, ty) = BlockGrid::cell_tile(x, y);
+            let tile = self.tile_area(tx, ty);
+            Circle::new(
+                tile.top_left + Point::new(1, 1),
+                min(tile.size.width, tile.size.height) - 2,
+            )
+            .draw_styled(style, target)
+            .ok();
+            return;
+        }
         Circle::new(
             Point::new(
                 x as i32 * self.cell_size.width as i32 + 2,
@@ -51,28 +222,686 @@ impl<C: PixelColor> MazePainter<C> {
         .ok();
     }
 
-    fn get_cell_neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        let mut neighbors: Vec<(usize, usize)> = Vec::new();
-        if x > 0 && self.maze[(x, y)].west {
-            neighbors.push((x - 1, y));
+    /// Draw the walls and the items of a single cell
+    pub fn draw_cell<D>(&self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        self.draw_items(x, y, target);
+        self.draw_stairs(x, y, target);
+        self.draw_walls(x, y, target);
+    }
+
+    /// The cell with everything around it, spreading over the neighbors as far as the walls do
+    pub fn cell_area(&self, x: usize, y: usize) -> Rectangle {
+        let margin = self.wall_width().div_ceil(2);
+        Rectangle::new(
+            Point::new(
+                x as i32 * self.cell_size.width as i32 - margin as i32,
+                y as i32 * self.cell_size.height as i32 - margin as i32,
+            ),
+            Size::new(
+                self.cell_size.width + 2 * margin + 1,
+                self.cell_size.height + 2 * margin + 1,
+            ),
+        )
+        .translate(self.offset)
+    }
+
+    /// Draw the cell again from scratch: the background, the fill, the walls, the items,
+    /// the markers and the path through it. The walls of the cells around it are drawn
+    /// again as well, since clearing the cell cuts their ends.
+    pub fn redraw_cell<D>(&self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        if let Some(background) = self.background {
+            self.cell_area(x, y)
+                .into_styled(PrimitiveStyle::with_fill(background))
+                .draw(target)
+                .ok();
+        }
+        for direction in Direction::ALL {
+            let (dx, dy) = direction.offset();
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx >= 0
+                && ny >= 0
+                && (nx as usize) < self.maze.width
+                && (ny as usize) < self.maze.height
+                && self.is_revealed(nx as usize, ny as usize)
+            {
+                self.draw_walls(nx as usize, ny as usize, target);
+            }
+        }
+        if self.is_revealed(x, y) {
+            let cell = &self.maze[(x, y)];
+            if let (Some(color), true) = (self.visited_fill, cell.visited) {
+                self.fill_cell(x, y, color, target);
+            }
+            self.draw_cell(x, y, target);
+            if let (Some(style), true) = (&self.path_style, cell.visited) {
+                for next in self.maze.open_neighbors(x, y) {
+                    if self.maze[next].visited {
+                        self.draw_route((x, y), next, style, target);
+                    }
+                }
+            }
+        }
+        // The markers of the cells around can reach into the cleared area as well
+        let near = |(mx, my): (usize, usize)| x.abs_diff(mx) <= 1 && y.abs_diff(my) <= 1;
+        if self.markers && near(self.start) && self.is_revealed(self.start.0, self.start.1) {
+            self.draw_marker(self.start.0, self.start.1, &self.start_style, target);
+        }
+        if self.markers && near(self.goal) && self.is_revealed(self.goal.0, self.goal.1) {
+            self.draw_marker(self.goal.0, self.goal.1, &self.goal_style, target);
+        }
+    }
+
+    /// Draw a few cells again, see `redraw_cell`
+    pub fn redraw_cells<D, I>(&self, cells: I, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        for (x, y) in cells {
+            self.redraw_cell(x, y, target);
+        }
+    }
+
+    /// The cells the area touches, e.g. to redraw what was under a sprite
+    pub fn cells_in(&self, area: &Rectangle) -> Vec<(usize, usize)> {
+        let Some(bottom_right) = area.bottom_right() else {
+            return Vec::new();
+        };
+        let to_cell = |point: Point| {
+            let p = point - self.offset;
+            (
+                (p.x.max(0) as u32 / self.cell_size.width).min(self.maze.width as u32 - 1) as usize,
+                (p.y.max(0) as u32 / self.cell_size.height).min(self.maze.height as u32 - 1)
+                    as usize,
+            )
+        };
+        let (x0, y0) = to_cell(area.top_left);
+        let (x1, y1) = to_cell(bottom_right);
+        (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Only the walls of the cell
+    pub fn draw_walls<D>(&self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        if let Some(blocks) = &self.blocks {
+            let (tx, ty) = BlockGrid::cell_tile(x, y);
+            for ty in ty - 1..=ty + 1 {
+                for tx in tx - 1..=tx + 1 {
+                    if blocks[(tx, ty)] == Tile::Wall {
+                        self.draw_tiles(tx, tx, ty, target);
+                    }
+                }
+            }
+            return;
         }
-        if x < self.maze.width - 1 && self.maze[(x, y)].east {
-            neighbors.push((x + 1, y));
+        let cell = &self.maze[(x, y)];
+        if let Some(crossing) = cell.crossing {
+            self.draw_crossing(x, y, crossing, target);
+            return;
         }
-        if y > 0 && self.maze[(x, y)].north {
-            neighbors.push((x, y - 1));
+        let (x, y) = (x as i32, y as i32);
+        let corner = |cx: i32, cy: i32| {
+            Point::new(
+                cx * self.cell_size.width as i32,
+                cy * self.cell_size.height as i32,
+            )
+        };
+        if !cell.north {
+            self.draw_wall(corner(x, y), corner(x + 1, y), target);
+        }
+        if !cell.south {
+            self.draw_wall(corner(x, y + 1), corner(x + 1, y + 1), target);
         }
-        if y < self.maze.height - 1 && self.maze[(x, y)].south {
-            neighbors.push((x, y + 1));
+        if !cell.east {
+            self.draw_wall(corner(x + 1, y), corner(x + 1, y + 1), target);
+        }
+        if !cell.west {
+            self.draw_wall(corner(x, y), corner(x, y + 1), target);
         }
-        neighbors
+    }
+
+    /// The wall tiles from `x0` to `x1` on row `y` of the block grid, in one go
+    fn draw_tiles<D>(&self, x0: usize, x1: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let Some(color) = self.style.stroke_color else {
+            return;
+        };
+        let first = self.tile_area(x0, y);
+        let width = (x1 - x0 + 1) as u32 * first.size.width;
+        Rectangle::new(first.top_left, Size::new(width, first.size.height))
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(target)
+            .ok();
+    }
+
+    /// The wall tiles next to the shown cells, each row of tiles in as few rectangles as possible
+    fn draw_blocks<D>(&self, blocks: &BlockGrid, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        for y in 0..blocks.height {
+            let mut start = None;
+            for x in 0..=blocks.width {
+                let wall = x < blocks.width
+                    && blocks[(x, y)] == Tile::Wall
+                    && blocks
+                        .cells_around(x, y)
+                        .into_iter()
+                        .any(|(cx, cy)| self.is_revealed(cx, cy));
+                match (wall, start) {
+                    (true, None) => start = Some(x),
+                    (false, Some(from)) => {
+                        self.draw_tiles(from, x - 1, y, target);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// A straight wall between two corners, as a single rectangle. The ends reach half
+    /// the wall width past the corners so thick walls meet without a notch, or get
+    /// round caps with rounded corners.
+    fn draw_wall<D>(&self, from: Point, to: Point, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let Some(color) = self.style.stroke_color else {
+            return;
+        };
+        let width = self.style.stroke_width.max(1) as i32;
+        let rounded = self.rounded_corners && width >= 2;
+        let extension = if rounded { 0 } else { width / 2 };
+        let (top_left, size) = if from.y == to.y {
+            (
+                Point::new(from.x.min(to.x) - extension, from.y - width / 2),
+                Size::new(
+                    ((from.x - to.x).abs() + 1 + 2 * extension) as u32,
+                    width as u32,
+                ),
+            )
+        } else {
+            (
+                Point::new(from.x - width / 2, from.y.min(to.y) - extension),
+                Size::new(
+                    width as u32,
+                    ((from.y - to.y).abs() + 1 + 2 * extension) as u32,
+                ),
+            )
+        };
+        let fill = PrimitiveStyle::with_fill(color);
+        Rectangle::new(top_left, size)
+            .translate(self.offset)
+            .draw_styled(&fill, target)
+            .ok();
+        if rounded {
+            for end in [from, to] {
+                // Lined up with the rectangle, so it stays inside walls that go on
+                Circle::new(end - Point::new(width / 2, width / 2), width as u32)
+                    .translate(self.offset)
+                    .draw_styled(&fill, target)
+                    .ok();
+            }
+        }
+    }
+
+    /// Reveal the cells around the explored part of the maze and hide the rest,
+    /// the walls are painted as the cells get revealed
+    pub fn with_fog(mut self, visibility: Visibility) -> Self {
+        let mut fog = Fog::new(self.maze.width, self.maze.height, visibility);
+        for y in 0..self.maze.height {
+            for x in 0..self.maze.width {
+                if self.maze[(x, y)].visited {
+                    fog.reveal_from(&self.maze, x, y);
+                }
+            }
+        }
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Cells outside of the mask are never revealed
+    pub fn is_revealed(&self, x: usize, y: usize) -> bool {
+        if !self.maze[(x, y)].active {
+            return false;
+        }
+        match &self.fog {
+            Some(fog) => fog.is_revealed(x, y),
+            None => true,
+        }
+    }
+
+    /// Mark the cell as visited and draw the cells revealed from it,
+    /// the other end of a teleporter gets visited as well
+    pub fn visit<D>(&mut self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        self.maze[(x, y)].visited = true;
+        let index = y * self.maze.width + x;
+        if self.steps[index].is_none() {
+            self.steps[index] = self
+                .maze
+                .open_neighbors(x, y)
+                .into_iter()
+                .filter_map(|(nx, ny)| self.steps[ny * self.maze.width + nx])
+                .min()
+                .map(|step| step + 1);
+        }
+        if let Some(color) = self.visited_fill {
+            self.fill_cell(x, y, color, target);
+        }
+        if let Some(fog) = &mut self.fog {
+            for (cx, cy) in fog.reveal_from(&self.maze, x, y) {
+                if self.maze[(cx, cy)].active {
+                    self.draw_cell(cx, cy, target);
+                }
+            }
+        }
+        if let CellContent::Teleporter { to } = self.maze[(x, y)].content {
+            if !self.maze[to].visited {
+                self.steps[to.1 * self.maze.width + to.0] = Some(self.route_step(x, y) + 1);
+                self.visit(to.0, to.1, target);
+            }
+        }
+    }
+
+    pub fn with_item_style(mut self, item_style: ItemStyle<C>) -> Self {
+        self.item_style = Some(item_style);
+        self
+    }
+
+    /// Draw the content of the cell and the one-way arrow if any
+    pub fn draw_items<D>(&self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let Some(item_style) = &self.item_style else {
+            return;
+        };
+        let cell = &self.maze[(x, y)];
+        let (cx, cy) = self.get_cell_central_point(x, y);
+        let center = Point::new(cx, cy);
+        // Between thick block walls only the middle of the cell is free
+        let size = match self.blocks {
+            Some(_) => min(self.cell_size.width, self.cell_size.height) / 2 + 2,
+            None => min(self.cell_size.width, self.cell_size.height),
+        };
+        match cell.content {
+            CellContent::Empty => {}
+            CellContent::Coin => {
+                Circle::with_center(center, size / 3)
+                    .into_styled(PrimitiveStyle::with_fill(item_style.coin))
+                    .draw(target)
+                    .ok();
+            }
+            CellContent::Key(key) => {
+                Rectangle::with_center(center, Size::new(size / 3, size / 3))
+                    .into_styled(PrimitiveStyle::with_fill(item_style.key_color(key)))
+                    .draw(target)
+                    .ok();
+            }
+            CellContent::Door(key) => {
+                Rectangle::with_center(center, Size::new(size - 4, size - 4))
+                    .into_styled(PrimitiveStyle::with_stroke(item_style.key_color(key), 2))
+                    .draw(target)
+                    .ok();
+            }
+            CellContent::Teleporter { .. } => {
+                Circle::with_center(center, size / 2)
+                    .into_styled(PrimitiveStyle::with_stroke(item_style.teleporter, 2))
+                    .draw(target)
+                    .ok();
+            }
+        }
+        if let Some(direction) = cell.one_way {
+            // A small arrow on the passage, pointing to the only allowed direction
+            let (dx, dy) = direction.offset();
+            let r = size as i32 / 6;
+            let tip = center + Point::new(dx * size as i32 / 2, dy * size as i32 / 2);
+            let base = tip - Point::new(dx * r * 2, dy * r * 2);
+            let side = Point::new(dy * r, dx * r);
+            Triangle::new(tip, base + side, base - side)
+                .into_styled(PrimitiveStyle::with_fill(item_style.one_way))
+                .draw(target)
+                .ok();
+        }
+    }
+
+    /// The walls of the corridor going over run along the cell like a bridge,
+    /// the corridor going under only shows the walls at the mouths of the tunnel
+    fn draw_crossing<D>(&self, x: usize, y: usize, crossing: Crossing, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let w = self.cell_size.width as i32;
+        let h = self.cell_size.height as i32;
+        let left = x as i32 * w;
+        let top = y as i32 * h;
+        let (right, bottom) = (left + w, top + h);
+        let (inset_x, inset_y) = (w / 4, h / 4);
+        let lines = match crossing {
+            Crossing::NorthSouthOver => [
+                (
+                    Point::new(left + inset_x, top),
+                    Point::new(left + inset_x, bottom),
+                ),
+                (
+                    Point::new(right - inset_x, top),
+                    Point::new(right - inset_x, bottom),
+                ),
+                (
+                    Point::new(left, top + inset_y),
+                    Point::new(left + inset_x, top + inset_y),
+                ),
+                (
+                    Point::new(left, bottom - inset_y),
+                    Point::new(left + inset_x, bottom - inset_y),
+                ),
+                (
+                    Point::new(right - inset_x, top + inset_y),
+                    Point::new(right, top + inset_y),
+                ),
+                (
+                    Point::new(right - inset_x, bottom - inset_y),
+                    Point::new(right, bottom - inset_y),
+                ),
+            ],
+            Crossing::EastWestOver => [
+                (
+                    Point::new(left, top + inset_y),
+                    Point::new(right, top + inset_y),
+                ),
+                (
+                    Point::new(left, bottom - inset_y),
+                    Point::new(right, bottom - inset_y),
+                ),
+                (
+                    Point::new(left + inset_x, top),
+                    Point::new(left + inset_x, top + inset_y),
+                ),
+                (
+                    Point::new(right - inset_x, top),
+                    Point::new(right - inset_x, top + inset_y),
+                ),
+                (
+                    Point::new(left + inset_x, bottom - inset_y),
+                    Point::new(left + inset_x, bottom),
+                ),
+                (
+                    Point::new(right - inset_x, bottom - inset_y),
+                    Point::new(right - inset_x, bottom),
+                ),
+            ],
+        };
+        for (start, end) in lines {
+            Line::new(start, end)
+                .translate(self.offset)
+                .into_styled(self.style)
+                .draw(target)
+                .ok();
+        }
+    }
+
+    /// Update the screen after a step of the generation: erase the carved wall
+    /// and draw the two cells again
+    pub fn draw_generation_event<D>(&self, event: GenerationEvent, background: C, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let GenerationEvent::Carve { from, to } = event else {
+            return;
+        };
+        let w = self.cell_size.width as i32;
+        let h = self.cell_size.height as i32;
+        let (x, y) = (from.0.max(to.0) as i32, from.1.max(to.1) as i32);
+        // Leave the corners, they belong to the walls of the other cells as well
+        let wall = if from.0 != to.0 {
+            Line::new(
+                Point::new(x * w, y * h + 1),
+                Point::new(x * w, (y + 1) * h - 1),
+            )
+        } else {
+            Line::new(
+                Point::new(x * w + 1, y * h),
+                Point::new((x + 1) * w - 1, y * h),
+            )
+        };
+        wall.translate(self.offset)
+            .into_styled(PrimitiveStyle::with_stroke(
+                background,
+                self.style.stroke_width,
+            ))
+            .draw(target)
+            .ok();
+        self.draw_cell(from.0, from.1, target);
+        self.draw_cell(to.0, to.1, target);
+    }
+
+    /// Fill the inside of the cell, keeping its walls, items and marker
+    pub fn fill_cell<D>(&self, x: usize, y: usize, color: C, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let inside = if self.blocks.is_some() {
+            let (tx, ty) = BlockGrid::cell_tile(x, y);
+            self.tile_area(tx, ty)
+        } else {
+            let inset = self.style.stroke_width as i32;
+            let size = Size::new(
+                self.cell_size
+                    .width
+                    .saturating_sub((2 * inset as u32).saturating_sub(1)),
+                self.cell_size
+                    .height
+                    .saturating_sub((2 * inset as u32).saturating_sub(1)),
+            );
+            Rectangle::new(
+                Point::new(
+                    x as i32 * self.cell_size.width as i32 + inset,
+                    y as i32 * self.cell_size.height as i32 + inset,
+                ),
+                size,
+            )
+            .translate(self.offset)
+        };
+        inside
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(target)
+            .ok();
+        self.draw_items(x, y, target);
+        self.draw_stairs(x, y, target);
+        if self.markers && ((x, y) == self.start || (x, y) == self.goal) {
+            let style = if (x, y) == self.start {
+                &self.start_style
+            } else {
+                &self.goal_style
+            };
+            self.draw_marker(x, y, style, target);
+        }
+    }
+
+    /// Show a step of a search, the cells are colored as they are explored
+    /// and the path is drawn when it's found
+    pub fn draw_search_event<D>(&self, event: &SearchEvent, style: &SearchStyle<C>, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        match event {
+            SearchEvent::Frontier((x, y)) => self.fill_cell(*x, *y, style.frontier, target),
+            SearchEvent::Visit((x, y)) => self.fill_cell(*x, *y, style.visited, target),
+            SearchEvent::Backtrack((x, y)) => self.fill_cell(*x, *y, style.backtracked, target),
+            SearchEvent::Done(Some(path)) => {
+                for pair in path.windows(2) {
+                    let (ax, ay) = self.get_cell_central_point(pair[0].0, pair[0].1);
+                    let (bx, by) = self.get_cell_central_point(pair[1].0, pair[1].1);
+                    Line::new(Point::new(ax, ay), Point::new(bx, by))
+                        .draw_styled(&style.path, target)
+                        .ok();
+                }
+            }
+            SearchEvent::Done(None) => {}
+        }
+    }
+
+    /// Draw a triangle pointing up in the upper half of the cell for stairs going up,
+    /// and one pointing down in the lower half for stairs going down
+    pub fn draw_stairs<D>(&self, x: usize, y: usize, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let cell = &self.maze[(x, y)];
+        let (cx, cy) = self.get_cell_central_point(x, y);
+        let r = min(self.cell_size.width, self.cell_size.height) as i32 / 4;
+        if cell.up {
+            Triangle::new(
+                Point::new(cx, cy - 2 * r),
+                Point::new(cx - r, cy - 1),
+                Point::new(cx + r, cy - 1),
+            )
+            .draw_styled(&self.style, target)
+            .ok();
+        }
+        if cell.down {
+            Triangle::new(
+                Point::new(cx, cy + 2 * r),
+                Point::new(cx - r, cy + 1),
+                Point::new(cx + r, cy + 1),
+            )
+            .draw_styled(&self.style, target)
+            .ok();
+        }
+    }
+
+    /// Draw the explored path again, e.g. after the whole screen was cleared
+    pub fn draw_path<D>(&self, style: &PathStyle<C>, target: &mut D)
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let mut segments = Vec::new();
+        for y in 0..self.maze.height {
+            for x in 0..self.maze.width {
+                if !self.maze[(x, y)].visited {
+                    continue;
+                }
+                for direction in [Direction::East, Direction::South] {
+                    let Some((nx, ny)) = self.maze.neighbor(x, y, direction) else {
+                        continue;
+                    };
+                    if self.maze[(nx, ny)].visited {
+                        let step = self.route_step(x, y).max(self.route_step(nx, ny));
+                        segments.push((step, (x, y), (nx, ny)));
+                    }
+                }
+            }
+        }
+        // In the order they were explored, the later segments cover the joins
+        segments.sort_by_key(|(step, _, _)| *step);
+        for (_, a, b) in segments {
+            self.draw_route(a, b, style, target);
+        }
+    }
+
+    /// Steps from the start to the cell along the route, `0` if it was never reached
+    pub fn route_step(&self, x: usize, y: usize) -> usize {
+        self.steps[y * self.maze.width + x].unwrap_or(0)
+    }
+
+    /// The segment of the route between two visited cells next to each other,
+    /// going away from the start
+    fn draw_route<D>(
+        &self,
+        a: (usize, usize),
+        b: (usize, usize),
+        style: &PathStyle<C>,
+        target: &mut D,
+    ) where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        let (from, to) = if self.route_step(a.0, a.1) <= self.route_step(b.0, b.1) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let (fx, fy) = self.get_cell_central_point(from.0, from.1);
+        let (tx, ty) = self.get_cell_central_point(to.0, to.1);
+        style.draw_segment(
+            Point::new(fx, fy),
+            Point::new(tx, ty),
+            self.route_step(to.0, to.1),
+            target,
+        );
+    }
+
+    /// Take back the end of a branch of the route, only the cells under the erased segment
+    /// are drawn again. Returns `false` if the cell is not the end of a branch.
+    /// Keys and teleporters stay, a door or the other end may have been reached through them.
+    pub fn unvisit<D>(&mut self, x: usize, y: usize, target: &mut D) -> bool
+    where
+        D: embedded_graphics::prelude::DrawTarget<Color = C>,
+    {
+        if (x, y) == self.start || !self.maze[(x, y)].visited {
+            return false;
+        }
+        if matches!(
+            self.maze[(x, y)].content,
+            CellContent::Key(_) | CellContent::Teleporter { .. }
+        ) {
+            return false;
+        }
+        let visited: Vec<(usize, usize)> = self
+            .maze
+            .open_neighbors(x, y)
+            .into_iter()
+            .filter(|n| self.maze[*n].visited)
+            .collect();
+        let [previous] = visited[..] else {
+            return false;
+        };
+        self.maze[(x, y)].visited = false;
+        self.steps[y * self.maze.width + x] = None;
+        let mut cells = vec![(x, y)];
+        if let Some(style) = &self.path_style {
+            let (ax, ay) = self.get_cell_central_point(x, y);
+            let (bx, by) = self.get_cell_central_point(previous.0, previous.1);
+            cells = self.cells_in(&style.segment_area(Point::new(ax, ay), Point::new(bx, by)));
+        }
+        self.redraw_cells(cells, target);
+        true
+    }
+
+    /// Neighbors the cell can be entered from, with the keys collected so far
+    fn get_cell_neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let keys = self.maze.collected_keys();
+        Direction::ALL
+            .into_iter()
+            .filter_map(|d| {
+                let (nx, ny) = self.maze.neighbor(x, y, d)?;
+                self.maze.can_move(nx, ny, d.opposite(), keys)?;
+                Some((nx, ny))
+            })
+            .collect()
     }
 
     fn is_cell_clickable(&self, x: usize, y: usize) -> bool {
         if x >= self.maze.width || y >= self.maze.height {
             return false;
         }
-        if self.maze[(x, y)].visited {
+        // The crossings are passed through, never stopped at
+        if self.maze[(x, y)].visited || self.maze[(x, y)].crossing.is_some() {
             return false;
         }
         let neighbors = self.get_cell_neighbors(x, y);
@@ -84,7 +913,7 @@ impl<C: PixelColor> MazePainter<C> {
         false
     }
 
-    fn get_cell_central_point(&self, x: usize, y: usize) -> (i32, i32) {
+    pub fn get_cell_central_point(&self, x: usize, y: usize) -> (i32, i32) {
         (
             x as i32 * self.cell_size.width as i32
                 + self.cell_size.width as i32 / 2
@@ -147,7 +976,7 @@ impl<C: PixelColor> MazePainter<C> {
         cells
     }
 
-    fn point_to_cell(&self, mut x: i32, mut y: i32) -> Option<(usize, usize)> {
+    pub fn point_to_cell(&self, mut x: i32, mut y: i32) -> Option<(usize, usize)> {
         if x < self.offset.x {
             x = self.offset.x;
         }
@@ -196,13 +1025,13 @@ impl<C: PixelColor> MazePainter<C> {
         &mut self,
         x: i32,
         y: i32,
-        style: PrimitiveStyle<D::Color>,
+        style: PathStyle<D::Color>,
         target: &mut D,
     ) -> bool
     where
         D: embedded_graphics::prelude::DrawTarget<Color = C>,
     {
-        let cell = self.get_closest_clickable_cell(x, y, CLICK_APPROXIMATION);
+        let cell = self.get_closest_clickable_cell(x, y, self.click_approximation);
         if cell.is_none() {
             return false;
         }
@@ -212,28 +1041,20 @@ impl<C: PixelColor> MazePainter<C> {
         }
         let neighbors = self.get_cell_neighbors(x, y);
         info!("Cell {:?} has neighbors {:?}", (x, y), neighbors);
-        let mut ret = false;
-        for n in neighbors {
-            if self.maze[(n.0, n.1)].visited {
-                // Draw a line from (x, y) to (n.0, n.1)
-                let line = Line::new(
-                    Point::new(
-                        x as i32 * self.cell_size.width as i32 + self.cell_size.width as i32 / 2,
-                        y as i32 * self.cell_size.height as i32 + self.cell_size.height as i32 / 2,
-                    ),
-                    Point::new(
-                        n.0 as i32 * self.cell_size.width as i32 + self.cell_size.width as i32 / 2,
-                        n.1 as i32 * self.cell_size.height as i32
-                            + self.cell_size.height as i32 / 2,
-                    ),
-                );
-                info!("Drawing line from {:?} to {:?}", (x, y), n);
-                line.translate(self.offset).draw_styled(&style, target).ok();
-                self.maze[(x, y)].visited = true;
-                ret = true;
-            }
+        let visited: Vec<(usize, usize)> = neighbors
+            .into_iter()
+            .filter(|n| self.maze[*n].visited)
+            .collect();
+        if visited.is_empty() {
+            return false;
         }
-        ret
+        // Visit first, filling the cell would cover the lines
+        self.visit(x, y, target);
+        for n in visited {
+            info!("Drawing line from {:?} to {:?}", (x, y), n);
+            self.draw_route((x, y), n, &style, target);
+        }
+        true
     }
 }
 
@@ -248,83 +1069,40 @@ impl<C: PixelColor> Drawable for MazePainter<C> {
     {
         for y in 0..self.maze.height {
             for x in 0..self.maze.width {
-                let cell = &self.maze[(x, y)];
-                let x = x as i32;
-                let y = y as i32;
-                if !cell.north {
-                    Line::new(
-                        Point::new(
-                            x * self.cell_size.width as i32,
-                            y * self.cell_size.height as i32,
-                        ),
-                        Point::new(
-                            (x + 1) * self.cell_size.width as i32,
-                            y * self.cell_size.height as i32,
-                        ),
-                    )
-                    .translate(self.offset)
-                    .into_styled(self.style)
-                    .draw(target)
-                    .ok();
+                if !self.is_revealed(x, y) {
+                    continue;
                 }
-                if !cell.south {
-                    Line::new(
-                        Point::new(
-                            x * self.cell_size.width as i32,
-                            (y + 1) * self.cell_size.height as i32,
-                        ),
-                        Point::new(
-                            (x + 1) * self.cell_size.width as i32,
-                            (y + 1) * self.cell_size.height as i32,
-                        ),
-                    )
-                    .translate(self.offset)
-                    .into_styled(self.style)
-                    .draw(target)
-                    .ok();
+                let cell = &self.maze[(x, y)];
+                if let (Some(color), true) = (self.visited_fill, cell.visited) {
+                    self.fill_cell(x, y, color, target);
+                } else {
+                    self.draw_items(x, y, target);
+                    self.draw_stairs(x, y, target);
                 }
-                if !cell.east {
-                    Line::new(
-                        Point::new(
-                            (x + 1) * self.cell_size.width as i32,
-                            y * self.cell_size.height as i32,
-                        ),
-                        Point::new(
-                            (x + 1) * self.cell_size.width as i32,
-                            (y + 1) * self.cell_size.height as i32,
-                        ),
-                    )
-                    .translate(self.offset)
-                    .into_styled(self.style)
-                    .draw(target)
-                    .ok();
-                }
-                if !cell.west {
-                    Line::new(
-                        Point::new(
-                            x * self.cell_size.width as i32,
-                            y * self.cell_size.height as i32,
-                        ),
-                        Point::new(
-                            x * self.cell_size.width as i32,
-                            (y + 1) * self.cell_size.height as i32,
-                        ),
-                    )
-                    .translate(self.offset)
-                    .into_styled(self.style)
-                    .draw(target)
-                    .ok();
+                if let (Some(crossing), None) = (cell.crossing, &self.blocks) {
+                    self.draw_crossing(x, y, crossing, target);
                 }
             }
         }
-        // Draw start and end markers
-        self.draw_marker(0, 0, &self.style, target);
-        self.draw_marker(
-            self.maze.width - 1,
-            self.maze.height - 1,
-            &self.style,
-            target,
-        );
+        if let Some(blocks) = &self.blocks {
+            self.draw_blocks(blocks, target);
+        } else {
+            // The walls shared by two cells are drawn once, and the straight ones in one go
+            let cell_size = self.cell_size;
+            let corner = |(x, y): (usize, usize)| {
+                Point::new(
+                    (x as u32 * cell_size.width) as i32,
+                    (y as u32 * cell_size.height) as i32,
+                )
+            };
+            for run in wall_runs(&self.maze, |x, y| self.is_revealed(x, y)) {
+                self.draw_wall(corner(run.from), corner(run.to), target);
+            }
+        }
+        if self.markers {
+            self.draw_marker(self.start.0, self.start.1, &self.start_style, target);
+            self.draw_marker(self.goal.0, self.goal.1, &self.goal_style, target);
+        }
         Ok(())
     }
 }
@@ -340,3 +1118,77 @@ where
         .try_into()
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    fn painter(stroke_width: u32) -> MazePainter<Rgb565> {
+        let mut maze = Maze::new(4, 3);
+        maze.generate_seeded(0, 0, 2);
+        MazePainter::new(
+            maze,
+            PrimitiveStyle::with_stroke(Rgb565::WHITE, stroke_width),
+            Size::new(10, 10),
+            Point::zero(),
+        )
+        .without_markers()
+    }
+
+    #[test]
+    fn fill_cell_without_walls() {
+        let painter = painter(0);
+        let mut target = FrameBuffer::new(40, 30);
+        painter.fill_cell(1, 1, Rgb565::RED, &mut target);
+        assert_eq!(target.get_pixel(10, 10), Rgb565::RED);
+        assert_eq!(target.get_pixel(19, 19), Rgb565::RED);
+        assert_eq!(target.get_pixel(9, 10), Rgb565::BLACK);
+        assert_eq!(target.get_pixel(20, 20), Rgb565::BLACK);
+    }
+
+    #[test]
+    fn fill_cell_keeps_the_walls() {
+        let painter = painter(2);
+        let mut target = FrameBuffer::new(40, 30);
+        painter.fill_cell(1, 1, Rgb565::RED, &mut target);
+        assert_eq!(target.get_pixel(11, 11), Rgb565::BLACK);
+        assert_eq!(target.get_pixel(12, 12), Rgb565::RED);
+        assert_eq!(target.get_pixel(18, 18), Rgb565::RED);
+        assert_eq!(target.get_pixel(19, 19), Rgb565::BLACK);
+    }
+
+    #[test]
+    fn unvisit_takes_back_the_end_of_the_route() {
+        // A corridor along the top row
+        let mut maze = Maze::new(4, 1);
+        for x in 1..4 {
+            maze.carve((x - 1, 0), (x, 0));
+        }
+        // The background and the path style to erase and redraw the route with
+        let theme = Theme::CLASSIC;
+        let mut painter =
+            MazePainter::new(maze, theme.wall_style(), Size::new(10, 10), Point::zero())
+                .with_theme(&theme);
+        let mut target = FrameBuffer::new(40, 10);
+        let style = theme.path_style();
+        assert!(painter.on_click(15, 5, style, &mut target));
+        assert!(painter.on_click(25, 5, style, &mut target));
+        assert_ne!(target.get_pixel(20, 5), theme.background);
+
+        // Only the end of the route goes
+        assert!(!painter.unvisit(0, 0, &mut target));
+        assert!(!painter.unvisit(1, 0, &mut target));
+        assert!(!painter.unvisit(3, 0, &mut target));
+        assert!(painter.unvisit(2, 0, &mut target));
+        assert!(!painter.maze[(2, 0)].visited);
+        assert_eq!(target.get_pixel(20, 5), theme.background);
+        assert_ne!(target.get_pixel(10, 5), theme.background);
+
+        // The cell can be visited again
+        assert!(painter.on_click(25, 5, style, &mut target));
+        assert_eq!(painter.route_step(2, 0), 2);
+    }
+}
@@ -0,0 +1,126 @@
+/// The explored route drawn as a polyline through the cell centers, with round joins,
+/// colors changing along the way and arrows pointing away from the start
+use embedded_graphics::{
+    pixelcolor::{Rgb565, RgbColor},
+    prelude::{DrawTarget, PixelColor, Point},
+    primitives::{Circle, Line, Primitive, PrimitiveStyle, Rectangle, Triangle},
+    Drawable,
+};
+
+/// Number of colors in a gradient, the path goes through them and back
+pub const GRADIENT_COLORS: usize = 8;
+/// Steps drawn with each color of the gradient
+const STEPS_PER_COLOR: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PathStyle<C: PixelColor> {
+    pub colors: [C; GRADIENT_COLORS],
+    pub width: u32,
+    /// An arrow every this many steps
+    pub arrows: Option<usize>,
+}
+
+impl<C: PixelColor> PathStyle<C> {
+    pub const fn solid(color: C, width: u32) -> Self {
+        Self {
+            colors: [color; GRADIENT_COLORS],
+            width,
+            arrows: None,
+        }
+    }
+
+    pub const fn with_arrows(mut self, every: usize) -> Self {
+        self.arrows = Some(if every > 0 { every } else { 1 });
+        self
+    }
+
+    /// The color of the step, `0` is the start
+    pub fn color_at(&self, step: usize) -> C {
+        let cycle = 2 * (GRADIENT_COLORS - 1);
+        let position = (step / STEPS_PER_COLOR) % cycle;
+        if position < GRADIENT_COLORS {
+            self.colors[position]
+        } else {
+            self.colors[cycle - position]
+        }
+    }
+
+    /// The part of the screen the segment covers, arrows included
+    pub fn segment_area(&self, from: Point, to: Point) -> Rectangle {
+        let margin = self.width as i32 + 2;
+        let top_left = from.component_min(to) - Point::new(margin, margin);
+        let bottom_right = from.component_max(to) + Point::new(margin, margin);
+        Rectangle::with_corners(top_left, bottom_right)
+    }
+
+    /// The segment reaching the `step`th cell of the route, going from `from` to `to`
+    pub fn draw_segment<D>(&self, from: Point, to: Point, step: usize, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let color = self.color_at(step);
+        Line::new(from, to)
+            .into_styled(PrimitiveStyle::with_stroke(color, self.width))
+            .draw(target)
+            .ok();
+        // Round joins, the line ends are cut square
+        if self.width > 1 {
+            for end in [from, to] {
+                Circle::with_center(end, self.width)
+                    .into_styled(PrimitiveStyle::with_fill(color))
+                    .draw(target)
+                    .ok();
+            }
+        }
+        if let Some(every) = self.arrows {
+            if step % every == 0 {
+                self.draw_arrow(from, to, color, target);
+            }
+        }
+    }
+
+    /// An arrowhead in the middle of the segment, only along the grid
+    fn draw_arrow<D>(&self, from: Point, to: Point, color: C, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let delta = to - from;
+        let (dx, dy) = (delta.x.signum(), delta.y.signum());
+        if (dx != 0) == (dy != 0) {
+            return;
+        }
+        let r = self.width as i32 + 1;
+        let tip = from + Point::new(delta.x / 2 + dx * r, delta.y / 2 + dy * r);
+        let base = tip - Point::new(dx * r * 2, dy * r * 2);
+        let side = Point::new(dy * r, dx * r);
+        Triangle::new(tip, base + side, base - side)
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(target)
+            .ok();
+    }
+}
+
+impl PathStyle<Rgb565> {
+    /// Colors going from `from` to `to`, given as 8-bit channels
+    pub const fn gradient(from: (u8, u8, u8), to: (u8, u8, u8), width: u32) -> Self {
+        const fn mix(a: u8, b: u8, i: usize) -> u8 {
+            let (a, b) = (a as i32, b as i32);
+            (a + (b - a) * i as i32 / (GRADIENT_COLORS as i32 - 1)) as u8
+        }
+        let mut colors = [Rgb565::BLACK; GRADIENT_COLORS];
+        let mut i = 0;
+        while i < GRADIENT_COLORS {
+            colors[i] = Rgb565::new(
+                mix(from.0, to.0, i) >> 3,
+                mix(from.1, to.1, i) >> 2,
+                mix(from.2, to.2, i) >> 3,
+            );
+            i += 1;
+        }
+        Self {
+            colors,
+            width,
+            arrows: None,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+/// Game level power manager, applies the idle policy to the display, the backlight and the touch screen.
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::OutputPin,
+    i2c::{I2c, SevenBitAddress},
+};
+
+use crate::{
+    gt911::GT911,
+    idle::{IdleAction, IdlePolicy, IdleState, MAX_BRIGHTNESS},
+};
+
+/// While the display sleeps, the GT911 is woken up this often to look for a touch
+const TOUCH_POLL_PERIOD: Duration = Duration::from_secs(1);
+/// How long the GT911 stays awake for each look
+const TOUCH_POLL_WINDOW: Duration = Duration::from_millis(100);
+
+/// The display as seen by the power manager
+pub trait DisplaySleep {
+    fn sleep(&mut self) -> anyhow::Result<()>;
+
+    fn wake(&mut self) -> anyhow::Result<()>;
+}
+
+/// The backlight as seen by the power manager, brightness levels are in percent
+pub trait Dimmer {
+    fn level(&self) -> u8;
+
+    /// Set the brightness immediately, cancels any running fade
+    fn set_brightness(&mut self, level: u8) -> anyhow::Result<()>;
+
+    fn fade_to(&mut self, level: u8, duration: Duration, now: Instant);
+
+    /// Make progress on the fade
+    fn update(&mut self, now: Instant) -> anyhow::Result<()>;
+}
+
+/// The touch screen as seen by the power manager
+pub trait TouchSleep {
+    fn sleep(&mut self) -> anyhow::Result<()>;
+
+    fn wake(&mut self) -> anyhow::Result<()>;
+}
+
+impl<I2C, RST, DELAY> TouchSleep for GT911<I2C, RST, DELAY>
+where
+    I2C: I2c<SevenBitAddress>,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn sleep(&mut self) -> anyhow::Result<()> {
+        GT911::sleep(self).map_err(|err| anyhow!("Can't put the touch screen to sleep: {:?}", err))
+    }
+
+    fn wake(&mut self) -> anyhow::Result<()> {
+        GT911::wake(self).map_err(|err| anyhow!("Can't wake up the touch screen: {:?}", err))
+    }
+}
+
+pub struct PowerManager {
+    policy: IdlePolicy,
+    fade_duration: Duration,
+    /// The GT911 is in its sleep mode, it can't be read then
+    touch_sleeping: bool,
+    /// When to wake the sleeping GT911 up, or to put it back to sleep after a look
+    next_touch_poll: Instant,
+}
+
+impl PowerManager {
+    pub fn new(policy: IdlePolicy, fade_duration: Duration, now: Instant) -> Self {
+        Self {
+            policy,
+            fade_duration,
+            touch_sleeping: false,
+            next_touch_poll: now,
+        }
+    }
+
+    pub fn state(&self) -> IdleState {
+        self.policy.state()
+    }
+
+    /// The touches can be read, the GT911 is asleep most of the time while the display sleeps
+    pub fn touch_awake(&self) -> bool {
+        !self.touch_sleeping
+    }
+
+    /// Feed a touch into the policy, returns `true` if the touch was used to wake up the device
+    /// and should not be handled by the game
+    pub fn on_touch(
+        &mut self,
+        now: Instant,
+        display: &mut impl DisplaySleep,
+        backlight: &mut impl Dimmer,
+        touch: &mut impl TouchSleep,
+    ) -> anyhow::Result<bool> {
+        let action = self.policy.on_activity(now);
+        let woken = matches!(action, Some(IdleAction::Wake(_)));
+        self.apply(action, now, display, backlight, touch)?;
+        Ok(woken)
+    }
+
+    /// Called periodically. While the display sleeps the GT911 only wakes up now and then
+    /// to look for the touch waking everything up, its INT pin is not wired to wake us instead.
+    pub fn tick(
+        &mut self,
+        now: Instant,
+        display: &mut impl DisplaySleep,
+        backlight: &mut impl Dimmer,
+        touch: &mut impl TouchSleep,
+    ) -> anyhow::Result<()> {
+        let action = self.policy.tick(now);
+        self.apply(action, now, display, backlight, touch)?;
+        if self.policy.state() == IdleState::Sleeping && now >= self.next_touch_poll {
+            if self.touch_sleeping {
+                wake_touch(backlight, touch)?;
+                self.next_touch_poll = now + TOUCH_POLL_WINDOW;
+            } else {
+                touch.sleep()?;
+                self.next_touch_poll = now + TOUCH_POLL_PERIOD;
+            }
+            self.touch_sleeping = !self.touch_sleeping;
+        }
+        backlight.update(now)?;
+        Ok(())
+    }
+
+    /// Brightness while in use, the dimmed brightness stays as it is
+    pub fn set_brightness(
+        &mut self,
+        level: u8,
+        now: Instant,
+        display: &mut impl DisplaySleep,
+        backlight: &mut impl Dimmer,
+        touch: &mut impl TouchSleep,
+    ) -> anyhow::Result<()> {
+        let action = self.policy.set_active_brightness(level);
+        self.apply(action, now, display, backlight, touch)
+    }
+
+    fn apply(
+        &mut self,
+        action: Option<IdleAction>,
+        now: Instant,
+        display: &mut impl DisplaySleep,
+        backlight: &mut impl Dimmer,
+        touch: &mut impl TouchSleep,
+    ) -> anyhow::Result<()> {
+        match action {
+            Some(IdleAction::SetBrightness(level)) => {
+                backlight.fade_to(level, self.fade_duration, now);
+            }
+            Some(IdleAction::Sleep) => {
+                backlight.set_brightness(0)?;
+                display.sleep()?;
+                touch.sleep()?;
+                self.touch_sleeping = true;
+                self.next_touch_poll = now + TOUCH_POLL_PERIOD;
+            }
+            Some(IdleAction::Wake(level)) => {
+                // The frame buffer is flushed on wake, so the screen comes back exactly as it was
+                display.wake()?;
+                if self.touch_sleeping {
+                    wake_touch(backlight, touch)?;
+                    self.touch_sleeping = false;
+                }
+                backlight.fade_to(level, self.fade_duration, now);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// The GT911 is woken up with a reset, which needs the backlight pin high on this board,
+/// so the backlight is at full brightness for the time of the reset
+fn wake_touch(backlight: &mut impl Dimmer, touch: &mut impl TouchSleep) -> anyhow::Result<()> {
+    let level = backlight.level();
+    backlight.set_brightness(MAX_BRIGHTNESS)?;
+    touch.wake()?;
+    backlight.set_brightness(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::idle::Fade;
+
+    const DIM_AFTER: Duration = Duration::from_secs(30);
+    const SLEEP_AFTER: Duration = Duration::from_secs(120);
+    const FADE: Duration = Duration::from_secs(1);
+
+    struct Display {
+        sleeping: bool,
+    }
+
+    impl DisplaySleep for Display {
+        fn sleep(&mut self) -> anyhow::Result<()> {
+            self.sleeping = true;
+            Ok(())
+        }
+
+        fn wake(&mut self) -> anyhow::Result<()> {
+            self.sleeping = false;
+            Ok(())
+        }
+    }
+
+    /// The level is shared with the touch screen, to see the backlight pin during its resets
+    struct Light {
+        level: Rc<Cell<u8>>,
+        fade: Option<Fade>,
+    }
+
+    impl Dimmer for Light {
+        fn level(&self) -> u8 {
+            self.level.get()
+        }
+
+        fn set_brightness(&mut self, level: u8) -> anyhow::Result<()> {
+            self.fade = None;
+            self.level.set(level);
+            Ok(())
+        }
+
+        fn fade_to(&mut self, level: u8, duration: Duration, now: Instant) {
+            self.fade = Some(Fade::new(self.level.get(), level, now, duration));
+        }
+
+        fn update(&mut self, now: Instant) -> anyhow::Result<()> {
+            if let Some(fade) = self.fade {
+                self.level.set(fade.level_at(now));
+            }
+            Ok(())
+        }
+    }
+
+    struct Touch {
+        sleeping: bool,
+        backlight: Rc<Cell<u8>>,
+        /// Backlight level at each reset
+        resets: Vec<u8>,
+    }
+
+    impl TouchSleep for Touch {
+        fn sleep(&mut self) -> anyhow::Result<()> {
+            self.sleeping = true;
+            Ok(())
+        }
+
+        fn wake(&mut self) -> anyhow::Result<()> {
+            self.sleeping = false;
+            self.resets.push(self.backlight.get());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dims_sleeps_and_wakes_with_the_backlight_on_for_the_resets() {
+        let start = Instant::now();
+        let policy = IdlePolicy::new(DIM_AFTER, start).sleep_after(SLEEP_AFTER);
+        let mut power = PowerManager::new(policy, FADE, start);
+        let level = Rc::new(Cell::new(MAX_BRIGHTNESS));
+        let mut display = Display { sleeping: false };
+        let mut light = Light {
+            level: level.clone(),
+            fade: None,
+        };
+        let mut touch = Touch {
+            sleeping: false,
+            backlight: level.clone(),
+            resets: Vec::new(),
+        };
+        let mut tick = |power: &mut PowerManager, at: Duration| {
+            power
+                .tick(start + at, &mut display, &mut light, &mut touch)
+                .unwrap();
+        };
+
+        tick(&mut power, DIM_AFTER);
+        tick(&mut power, DIM_AFTER + FADE);
+        assert_eq!(power.state(), IdleState::Dimmed);
+        assert_eq!(level.get(), 10);
+
+        tick(&mut power, SLEEP_AFTER);
+        assert_eq!(power.state(), IdleState::Sleeping);
+        assert_eq!(level.get(), 0);
+        assert!(!power.touch_awake());
+
+        // The GT911 wakes up now and then to look for a touch, and goes back to sleep
+        tick(&mut power, SLEEP_AFTER + TOUCH_POLL_PERIOD);
+        assert!(power.touch_awake());
+        assert_eq!(level.get(), 0);
+        tick(
+            &mut power,
+            SLEEP_AFTER + TOUCH_POLL_PERIOD + TOUCH_POLL_WINDOW,
+        );
+        assert!(!power.touch_awake());
+        tick(
+            &mut power,
+            SLEEP_AFTER + 2 * TOUCH_POLL_PERIOD + TOUCH_POLL_WINDOW,
+        );
+        assert!(power.touch_awake());
+
+        // Touched during a look
+        let touched = start + SLEEP_AFTER + 2 * TOUCH_POLL_PERIOD + 2 * TOUCH_POLL_WINDOW / 3;
+        assert!(power
+            .on_touch(touched, &mut display, &mut light, &mut touch)
+            .unwrap());
+        assert_eq!(power.state(), IdleState::Active);
+        assert!(power.touch_awake());
+        light.update(touched + FADE).unwrap();
+        assert!(!display.sleeping);
+        assert!(!touch.sleeping);
+        assert_eq!(level.get(), MAX_BRIGHTNESS);
+        assert_eq!(touch.resets, [MAX_BRIGHTNESS, MAX_BRIGHTNESS]);
+    }
+}
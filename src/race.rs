@@ -0,0 +1,134 @@
+/// Two-player race, both players get the same maze side by side on the screen
+/// and tap their way to the goal at the same time.
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use log::info;
+
+use crate::{gt911::TouchPoint, maze::Maze, maze_painter::MazePainter, path::PathStyle};
+
+pub const PLAYERS: usize = 2;
+
+pub struct Race<C: PixelColor> {
+    pub players: [MazePainter<C>; PLAYERS],
+    // Half of the screen each player owns
+    areas: [Rectangle; PLAYERS],
+    winner: Option<usize>,
+}
+
+impl<C: PixelColor> Race<C> {
+    /// Generate two identical mazes from `seed`, each one centered in its half of the screen
+    pub fn new(
+        maze_size: (usize, usize),
+        seed: u64,
+        style: PrimitiveStyle<C>,
+        cell_size: Size,
+        screen_size: Size,
+    ) -> Self {
+        let half = Size::new(screen_size.width / 2, screen_size.height);
+        let areas = [
+            Rectangle::new(Point::zero(), half),
+            Rectangle::new(Point::new(half.width as i32, 0), half),
+        ];
+        let maze_pixels = Size::new(
+            maze_size.0 as u32 * cell_size.width,
+            maze_size.1 as u32 * cell_size.height,
+        );
+        let players = areas.map(|area| {
+            let mut maze = Maze::new(maze_size.0, maze_size.1);
+            maze.generate_seeded(0, 0, seed);
+            let offset = area.top_left + (area.size - maze_pixels) / 2;
+            MazePainter::new(maze, style, cell_size, offset)
+        });
+        Self {
+            players,
+            areas,
+            winner: None,
+        }
+    }
+
+    /// The player owning the half of the screen the point is in
+    pub fn player_at(&self, point: Point) -> Option<usize> {
+        self.areas.iter().position(|area| area.contains(point))
+    }
+
+    /// Route the touch points to the players, returns the winner once a player reaches the goal
+    pub fn on_touches<D>(
+        &mut self,
+        touches: &[TouchPoint],
+        path_style: PathStyle<C>,
+        target: &mut D,
+    ) -> Option<usize>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        if self.winner.is_some() {
+            return self.winner;
+        }
+        for touch in touches {
+            let point = Point::new(touch.x as i32, touch.y as i32);
+            let Some(player) = self.player_at(point) else {
+                continue;
+            };
+            // Keep the point inside the player's maze, touches on the border are near misses
+            let painter = &mut self.players[player];
+            let maze_area = Rectangle::new(
+                painter.offset,
+                Size::new(
+                    painter.maze.width as u32 * painter.cell_size.width,
+                    painter.maze.height as u32 * painter.cell_size.height,
+                ),
+            );
+            let Some(bottom_right) = maze_area.bottom_right() else {
+                continue;
+            };
+            let point = point
+                .component_max(maze_area.top_left)
+                .component_min(bottom_right);
+            if !painter.on_click(point.x, point.y, path_style, target) {
+                continue;
+            }
+            if painter.is_solved() {
+                info!("Player {} wins", player + 1);
+                self.winner = Some(player);
+                break;
+            }
+        }
+        self.winner
+    }
+
+    /// Frame the half of the winner
+    pub fn draw_winner<D>(&self, style: PrimitiveStyle<C>, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        if let Some(winner) = self.winner {
+            let area = self.areas[winner];
+            area.resized(
+                area.size - Size::new(4, 4),
+                embedded_graphics::geometry::AnchorPoint::Center,
+            )
+            .into_styled(style)
+            .draw(target)
+            .ok();
+        }
+    }
+}
+
+impl<C: PixelColor> Drawable for Race<C> {
+    type Color = C;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        for painter in &self.players {
+            painter.draw(target)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+/// The "runner" mode, a token sits in the current cell and slides through the corridors
+/// instead of the player tapping the cells one by one.
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::{Circle, Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+
+use crate::{
+    maze::{CellContent, Direction, Maze},
+    maze_painter::MazePainter,
+};
+
+/// Cells passed by the token in a slide, the first one is where it starts
+struct Slide {
+    path: Vec<(usize, usize)>,
+    start: Instant,
+}
+
+pub struct Runner<C: PixelColor> {
+    position: (usize, usize),
+    slide: Option<Slide>,
+    style: PrimitiveStyle<C>,
+    background: C,
+    cell_duration: Duration,
+    // Where the token was drawn last time, needs to be erased before drawing it again
+    drawn: Option<Rectangle>,
+}
+
+/// Follow the corridor from `from` in `direction`, turning with it, until reaching a junction,
+/// a dead end, a cell with an item or `stop_at`. Returns only `from` if the way is blocked
+/// by a wall, a locked door or a one-way passage.
+pub fn corridor(
+    maze: &Maze,
+    from: (usize, usize),
+    direction: Direction,
+    stop_at: (usize, usize),
+) -> Vec<(usize, usize)> {
+    let keys = maze.collected_keys();
+    let mut path = vec![from];
+    let mut current = from;
+    let mut direction = direction;
+    while let Some(next) = maze.can_move(current.0, current.1, direction, keys) {
+        path.push(next);
+        current = next;
+        if current == stop_at || maze[current].content != CellContent::Empty {
+            break;
+        }
+        let exits: Vec<Direction> = maze
+            .open_directions(current.0, current.1)
+            .into_iter()
+            .filter(|d| {
+                *d != direction.opposite()
+                    && maze.can_move(current.0, current.1, *d, keys).is_some()
+            })
+            .collect();
+        // Only keep going in a corridor, i.e. a cell with exactly one way out
+        if exits.len() != 1 {
+            break;
+        }
+        direction = exits[0];
+    }
+    path
+}
+
+impl<C: PixelColor> Runner<C> {
+    pub fn new(position: (usize, usize), style: PrimitiveStyle<C>, background: C) -> Self {
+        Self {
+            position,
+            slide: None,
+            style,
+            background,
+            cell_duration: Duration::from_millis(60),
+            drawn: None,
+        }
+    }
+
+    /// The color the token is erased with, e.g. after switching the theme
+    pub fn set_background(&mut self, background: C) {
+        self.background = background;
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.slide.is_some()
+    }
+
+    /// Hold the slide for the time the game was paused, e.g. while the settings were shown
+    pub fn delay(&mut self, paused: Duration) {
+        if let Some(slide) = &mut self.slide {
+            slide.start += paused;
+        }
+    }
+
+    /// Start sliding in the direction, returns `false` if the move is blocked by a wall
+    /// or the token is still moving.
+    pub fn start_move(
+        &mut self,
+        maze: &Maze,
+        direction: Direction,
+        stop_at: (usize, usize),
+        now: Instant,
+    ) -> bool {
+        if self.is_moving() {
+            return false;
+        }
+        let path = corridor(maze, self.position, direction, stop_at);
+        if path.len() < 2 {
+            return false;
+        }
+        self.slide = Some(Slide { path, start: now });
+        true
+    }
+
+    /// Advance the animation, the cells the token passed are marked as visited.
+    /// Returns `true` when the token arrives at the end of the slide.
+    pub fn update<D>(&mut self, painter: &mut MazePainter<C>, now: Instant, target: &mut D) -> bool
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let Some(slide) = &self.slide else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(slide.start).as_millis() as u32;
+        let cell_ms = self.cell_duration.as_millis().max(1) as u32;
+        let step = (elapsed / cell_ms) as usize;
+        let last = slide.path.len() - 1;
+        for cell in &slide.path[..=step.min(last)] {
+            if !painter.maze[*cell].visited {
+                painter.visit(cell.0, cell.1, target);
+            }
+        }
+        if step >= last {
+            // Step out at the other end of a teleporter
+            self.position = match painter.maze[slide.path[last]].content {
+                CellContent::Teleporter { to } => to,
+                _ => slide.path[last],
+            };
+            self.slide = None;
+            self.draw(painter, target);
+            return true;
+        }
+        // Interpolate between the centers of the two cells
+        let (x0, y0) = painter.get_cell_central_point(slide.path[step].0, slide.path[step].1);
+        let (x1, y1) =
+            painter.get_cell_central_point(slide.path[step + 1].0, slide.path[step + 1].1);
+        let t = (elapsed % cell_ms) as i32;
+        let center = Point::new(
+            x0 + (x1 - x0) * t / cell_ms as i32,
+            y0 + (y1 - y0) * t / cell_ms as i32,
+        );
+        self.position = slide.path[step];
+        self.draw_at(center, painter, target);
+        false
+    }
+
+    /// Draw the token in the current cell
+    pub fn draw<D>(&mut self, painter: &MazePainter<C>, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let (x, y) = painter.get_cell_central_point(self.position.0, self.position.1);
+        self.draw_at(Point::new(x, y), painter, target);
+    }
+
+    fn draw_at<D>(&mut self, center: Point, painter: &MazePainter<C>, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // Keep the token away from the walls so erasing it doesn't touch them
+        let diameter = painter
+            .cell_size
+            .width
+            .min(painter.cell_size.height)
+            .saturating_sub(6);
+        let token = Circle::with_center(center, diameter);
+        if let Some(area) = self.drawn.take() {
+            area.into_styled(PrimitiveStyle::with_fill(self.background))
+                .draw(target)
+                .ok();
+            // Bring back the fill and the items of the cells under the token
+            painter.redraw_cells(painter.cells_in(&area), target);
+        }
+        token.into_styled(self.style).draw(target).ok();
+        self.drawn = Some(Rectangle::new(
+            token.top_left,
+            Size::new(diameter + 1, diameter + 1),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    /// A corridor from west to east with a side branch going south from the middle
+    fn branched() -> Maze {
+        let mut maze = Maze::new(6, 2);
+        for x in 1..6 {
+            maze.carve((x - 1, 0), (x, 0));
+        }
+        maze.carve((3, 0), (3, 1));
+        maze
+    }
+
+    fn painter(maze: Maze) -> MazePainter<Rgb565> {
+        MazePainter::new(
+            maze,
+            PrimitiveStyle::with_stroke(Rgb565::WHITE, 1),
+            Size::new(10, 10),
+            Point::zero(),
+        )
+    }
+
+    #[test]
+    fn corridor_stops_at_junctions_and_walls() {
+        let maze = branched();
+        assert_eq!(
+            corridor(&maze, (0, 0), Direction::East, (5, 0)),
+            [(0, 0), (1, 0), (2, 0), (3, 0)]
+        );
+        assert_eq!(
+            corridor(&maze, (3, 0), Direction::East, (5, 0)),
+            [(3, 0), (4, 0), (5, 0)]
+        );
+        // Turns with the corridor
+        assert_eq!(
+            corridor(&maze, (3, 1), Direction::North, (5, 0)),
+            [(3, 1), (3, 0)]
+        );
+        assert_eq!(corridor(&maze, (0, 0), Direction::South, (5, 0)), [(0, 0)]);
+        // The goal is a stop
+        assert_eq!(
+            corridor(&maze, (3, 0), Direction::East, (4, 0)),
+            [(3, 0), (4, 0)]
+        );
+    }
+
+    #[test]
+    fn doors_need_their_key() {
+        let mut maze = branched();
+        maze[(2, 0)].content = CellContent::Door(0);
+        assert_eq!(
+            corridor(&maze, (0, 0), Direction::East, (5, 0)),
+            [(0, 0), (1, 0)]
+        );
+        maze[(0, 0)].content = CellContent::Key(0);
+        maze[(0, 0)].visited = true;
+        // The door is an item, the slide stops on it
+        assert_eq!(
+            corridor(&maze, (0, 0), Direction::East, (5, 0)),
+            [(0, 0), (1, 0), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn one_way_passages_are_only_walked_out() {
+        let mut maze = branched();
+        // The passage from (4, 0) to the west can't be walked back east
+        maze[(4, 0)].one_way = Some(Direction::West);
+        assert_eq!(corridor(&maze, (3, 0), Direction::East, (5, 0)), [(3, 0)]);
+        assert_eq!(
+            corridor(&maze, (5, 0), Direction::West, (0, 0)),
+            [(5, 0), (4, 0), (3, 0)]
+        );
+    }
+
+    #[test]
+    fn slides_over_time() {
+        let mut painter = painter(branched());
+        let mut target = FrameBuffer::new(60, 20);
+        let now = Instant::now();
+        let mut runner = Runner::new(
+            (0, 0),
+            PrimitiveStyle::with_fill(Rgb565::RED),
+            Rgb565::BLACK,
+        );
+        assert!(!runner.start_move(&painter.maze, Direction::South, (5, 0), now));
+        assert!(runner.start_move(&painter.maze, Direction::East, (5, 0), now));
+        assert!(!runner.start_move(&painter.maze, Direction::West, (5, 0), now));
+        // Two cells in after two cell durations
+        assert!(!runner.update(&mut painter, now + Duration::from_millis(130), &mut target));
+        assert_eq!(runner.position(), (2, 0));
+        assert!(painter.maze[(2, 0)].visited);
+        assert!(!painter.maze[(3, 0)].visited);
+        // Held while paused
+        runner.delay(Duration::from_secs(1));
+        assert!(!runner.update(&mut painter, now + Duration::from_millis(200), &mut target));
+        assert!(!painter.maze[(3, 0)].visited);
+        assert!(runner.update(&mut painter, now + Duration::from_secs(2), &mut target));
+        assert_eq!(runner.position(), (3, 0));
+        assert!(!runner.is_moving());
+    }
+
+    #[test]
+    fn teleporters_move_the_runner() {
+        let mut maze = branched();
+        maze[(2, 0)].content = CellContent::Teleporter { to: (3, 1) };
+        let mut painter = painter(maze);
+        let mut target = FrameBuffer::new(60, 20);
+        let now = Instant::now();
+        let mut runner = Runner::new(
+            (0, 0),
+            PrimitiveStyle::with_fill(Rgb565::RED),
+            Rgb565::BLACK,
+        );
+        assert!(runner.start_move(&painter.maze, Direction::East, (5, 0), now));
+        assert!(runner.update(&mut painter, now + Duration::from_secs(1), &mut target));
+        assert_eq!(runner.position(), (3, 1));
+    }
+}
@@ -0,0 +1,448 @@
+/// Step-wise maze solvers, one event at a time so the search can be watched.
+/// They only follow the walls, items and doors are ignored.
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use crate::maze::{Direction, Maze};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SearchAlgorithm {
+    BreadthFirst,
+    DepthFirst,
+    /// Best first with the Manhattan distance to the goal
+    AStar,
+    /// Keep the left hand on the wall
+    WallFollower,
+    /// Mark every passage taken, never take a passage marked twice
+    Tremaux,
+}
+
+impl SearchAlgorithm {
+    pub const ALL: [SearchAlgorithm; 5] = [
+        SearchAlgorithm::BreadthFirst,
+        SearchAlgorithm::DepthFirst,
+        SearchAlgorithm::AStar,
+        SearchAlgorithm::WallFollower,
+        SearchAlgorithm::Tremaux,
+    ];
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// The cell was found and waits to be visited
+    Frontier((usize, usize)),
+    Visit((usize, usize)),
+    /// The cell is a dead end, the search goes back from it
+    Backtrack((usize, usize)),
+    /// The path from the start to the goal, `None` if there is none
+    Done(Option<Vec<(usize, usize)>>),
+}
+
+// A* cell with the estimated total cost and the cost so far, the lowest one first
+type Candidate = Reverse<(usize, usize, (usize, usize))>;
+
+pub struct Search {
+    algorithm: SearchAlgorithm,
+    start: (usize, usize),
+    goal: (usize, usize),
+    width: usize,
+    events: VecDeque<SearchEvent>,
+    // Where each cell was reached from, for the searches building a tree
+    came_from: Vec<Option<(usize, usize)>>,
+    seen: Vec<bool>,
+    queue: VecDeque<(usize, usize)>,
+    heap: BinaryHeap<Candidate>,
+    cost: Vec<usize>,
+    // The way from the start to the current cell, for the walking searches
+    stack: Vec<(usize, usize)>,
+    heading: Direction,
+    // Trémaux marks of each passage, by cell and direction
+    marks: Vec<[u8; 4]>,
+    steps: usize,
+    done: bool,
+}
+
+fn direction_index(direction: Direction) -> usize {
+    Direction::ALL.iter().position(|d| *d == direction).unwrap()
+}
+
+fn distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+impl Search {
+    pub fn new(
+        maze: &Maze,
+        algorithm: SearchAlgorithm,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Self {
+        let cells = maze.width * maze.height;
+        let mut search = Self {
+            algorithm,
+            start,
+            goal,
+            width: maze.width,
+            events: VecDeque::new(),
+            came_from: vec![None; cells],
+            seen: vec![false; cells],
+            queue: VecDeque::new(),
+            heap: BinaryHeap::new(),
+            cost: vec![usize::MAX; cells],
+            stack: vec![start],
+            heading: Direction::East,
+            marks: vec![[0; 4]; cells],
+            steps: 0,
+            done: false,
+        };
+        let i = search.index(start);
+        search.seen[i] = true;
+        search.queue.push_back(start);
+        search.cost[i] = 0;
+        search.heap.push(Reverse((distance(start, goal), 0, start)));
+        search.events.push_back(SearchEvent::Frontier(start));
+        search
+    }
+
+    pub fn algorithm(&self) -> SearchAlgorithm {
+        self.algorithm
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    fn index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.width + x
+    }
+
+    /// The next event of the search, `None` after `SearchEvent::Done`
+    pub fn step(&mut self, maze: &Maze) -> Option<SearchEvent> {
+        while self.events.is_empty() && !self.done {
+            match self.algorithm {
+                SearchAlgorithm::BreadthFirst => self.step_breadth_first(maze),
+                SearchAlgorithm::DepthFirst => self.step_depth_first(maze),
+                SearchAlgorithm::AStar => self.step_a_star(maze),
+                SearchAlgorithm::WallFollower => self.step_wall_follower(maze),
+                SearchAlgorithm::Tremaux => self.step_tremaux(maze),
+            }
+        }
+        self.events.pop_front()
+    }
+
+    fn finish(&mut self, path: Option<Vec<(usize, usize)>>) {
+        self.events.push_back(SearchEvent::Done(path));
+        self.done = true;
+    }
+
+    fn tree_path(&self) -> Vec<(usize, usize)> {
+        let mut path = vec![self.goal];
+        let mut current = self.goal;
+        while let Some(previous) = self.came_from[self.index(current)] {
+            path.push(previous);
+            current = previous;
+        }
+        path.reverse();
+        path
+    }
+
+    fn step_breadth_first(&mut self, maze: &Maze) {
+        let Some(cell) = self.queue.pop_front() else {
+            self.finish(None);
+            return;
+        };
+        self.events.push_back(SearchEvent::Visit(cell));
+        if cell == self.goal {
+            self.finish(Some(self.tree_path()));
+            return;
+        }
+        for next in maze.open_neighbors(cell.0, cell.1) {
+            let i = self.index(next);
+            if !self.seen[i] {
+                self.seen[i] = true;
+                self.came_from[i] = Some(cell);
+                self.queue.push_back(next);
+                self.events.push_back(SearchEvent::Frontier(next));
+            }
+        }
+    }
+
+    fn step_depth_first(&mut self, maze: &Maze) {
+        let Some(&cell) = self.stack.last() else {
+            self.finish(None);
+            return;
+        };
+        if cell == self.goal {
+            self.finish(Some(self.stack.clone()));
+            return;
+        }
+        let next = maze
+            .open_neighbors(cell.0, cell.1)
+            .into_iter()
+            .find(|next| !self.seen[self.index(*next)]);
+        match next {
+            Some(next) => {
+                let i = self.index(next);
+                self.seen[i] = true;
+                self.stack.push(next);
+                self.events.push_back(SearchEvent::Visit(next));
+            }
+            None => {
+                self.stack.pop();
+                self.events.push_back(SearchEvent::Backtrack(cell));
+            }
+        }
+    }
+
+    fn step_a_star(&mut self, maze: &Maze) {
+        let Some(Reverse((_, cost, cell))) = self.heap.pop() else {
+            self.finish(None);
+            return;
+        };
+        // Already visited with a lower cost
+        if cost > self.cost[self.index(cell)] {
+            return;
+        }
+        self.events.push_back(SearchEvent::Visit(cell));
+        if cell == self.goal {
+            self.finish(Some(self.tree_path()));
+            return;
+        }
+        for next in maze.open_neighbors(cell.0, cell.1) {
+            // Passing a crossing is two cells long
+            let next_cost = cost + distance(cell, next);
+            let i = self.index(next);
+            if next_cost < self.cost[i] {
+                self.cost[i] = next_cost;
+                self.came_from[i] = Some(cell);
+                self.heap.push(Reverse((
+                    next_cost + distance(next, self.goal),
+                    next_cost,
+                    next,
+                )));
+                self.events.push_back(SearchEvent::Frontier(next));
+            }
+        }
+    }
+
+    /// Go to the cell, or back if it's where we came from, the stack stays a path without loops
+    fn walk_to(&mut self, cell: (usize, usize)) {
+        if self.stack.len() >= 2 && self.stack[self.stack.len() - 2] == cell {
+            let left = self.stack.pop().unwrap();
+            self.events.push_back(SearchEvent::Backtrack(left));
+        } else if let Some(i) = self.stack.iter().position(|c| *c == cell) {
+            // Walked around a loop, drop it
+            for left in self.stack.drain(i + 1..).rev() {
+                self.events.push_back(SearchEvent::Backtrack(left));
+            }
+        } else {
+            self.stack.push(cell);
+            self.events.push_back(SearchEvent::Visit(cell));
+        }
+    }
+
+    fn step_wall_follower(&mut self, maze: &Maze) {
+        let &cell = self.stack.last().unwrap();
+        if cell == self.goal {
+            self.finish(Some(self.stack.clone()));
+            return;
+        }
+        let heading = self.heading;
+        let Some((direction, next)) =
+            [heading.left(), heading, heading.right(), heading.opposite()]
+                .into_iter()
+                .find_map(|d| maze.neighbor(cell.0, cell.1, d).map(|next| (d, next)))
+        else {
+            self.finish(None);
+            return;
+        };
+        // Every passage is walked at most twice before the hand is back where it started,
+        // the goal is not on this wall then
+        self.steps += 1;
+        if self.steps > 2 * self.seen.len() {
+            self.finish(None);
+            return;
+        }
+        self.heading = direction;
+        self.walk_to(next);
+    }
+
+    fn step_tremaux(&mut self, maze: &Maze) {
+        let &cell = self.stack.last().unwrap();
+        if cell == self.goal {
+            self.finish(Some(self.stack.clone()));
+            return;
+        }
+        let i = self.index(cell);
+        let exits: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|d| maze.neighbor(cell.0, cell.1, *d).is_some())
+            .collect();
+        let came_back = self.heading.opposite();
+        let entered_mark = if exits.contains(&came_back) && cell != self.start {
+            self.marks[i][direction_index(came_back)]
+        } else {
+            0
+        };
+        let junction_seen = exits
+            .iter()
+            .filter(|d| **d != came_back)
+            .any(|d| self.marks[i][direction_index(*d)] > 0);
+        // A known cell reached by a new passage: go back the same way
+        let direction = if entered_mark == 1 && junction_seen {
+            Some(came_back)
+        } else {
+            exits
+                .iter()
+                .copied()
+                .filter(|d| self.marks[i][direction_index(*d)] < 2)
+                .min_by_key(|d| (self.marks[i][direction_index(*d)], *d == came_back))
+        };
+        let Some(direction) = direction else {
+            self.finish(None);
+            return;
+        };
+        let next = maze.neighbor(cell.0, cell.1, direction).unwrap();
+        let j = self.index(next);
+        self.marks[i][direction_index(direction)] += 1;
+        self.marks[j][direction_index(direction.opposite())] += 1;
+        self.heading = direction;
+        self.walk_to(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver;
+
+    /// Run the search to the end, returns the path it found
+    fn run(
+        maze: &Maze,
+        algorithm: SearchAlgorithm,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Option<Vec<(usize, usize)>> {
+        let mut search = Search::new(maze, algorithm, start, goal);
+        for _ in 0..100_000 {
+            match search.step(maze) {
+                Some(SearchEvent::Done(path)) => {
+                    assert!(search.is_done());
+                    assert_eq!(search.step(maze), None);
+                    return path;
+                }
+                Some(_) => {}
+                None => panic!("{:?} stopped without being done", algorithm),
+            }
+        }
+        panic!("{:?} never finished", algorithm);
+    }
+
+    fn assert_walkable(
+        maze: &Maze,
+        path: &[(usize, usize)],
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) {
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        for step in path.windows(2) {
+            assert!(maze.open_neighbors(step[0].0, step[0].1).contains(&step[1]));
+        }
+    }
+
+    /// A ring around the middle cell, which is only open to the north if at all.
+    /// The walls around the middle cell don't touch the outer wall.
+    fn ring(open_middle: bool) -> Maze {
+        let mut maze = Maze::new(3, 3);
+        let ring = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (1, 2),
+            (0, 2),
+            (0, 1),
+            (0, 0),
+        ];
+        for pair in ring.windows(2) {
+            maze.carve(pair[0], pair[1]);
+        }
+        if open_middle {
+            maze.carve((1, 0), (1, 1));
+        }
+        maze
+    }
+
+    #[test]
+    fn every_algorithm_solves_a_generated_maze() {
+        let mut maze = Maze::new(12, 8);
+        maze.generate_seeded(0, 0, 3);
+        let shortest = solver::solve(&maze, (0, 0), (11, 7)).unwrap();
+        for algorithm in SearchAlgorithm::ALL {
+            let path = run(&maze, algorithm, (0, 0), (11, 7)).unwrap();
+            assert_walkable(&maze, &path, (0, 0), (11, 7));
+            // A perfect maze has a single way without loops
+            assert_eq!(path, shortest, "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn every_algorithm_solves_a_maze_with_loops() {
+        let mut maze = Maze::new(5, 5);
+        for y in 0..5 {
+            for x in 0..5 {
+                if x + 1 < 5 {
+                    maze.carve((x, y), (x + 1, y));
+                }
+                if y + 1 < 5 {
+                    maze.carve((x, y), (x, y + 1));
+                }
+            }
+        }
+        for algorithm in SearchAlgorithm::ALL {
+            let path = run(&maze, algorithm, (0, 0), (4, 4)).unwrap();
+            assert_walkable(&maze, &path, (0, 0), (4, 4));
+            if matches!(
+                algorithm,
+                SearchAlgorithm::BreadthFirst | SearchAlgorithm::AStar
+            ) {
+                assert_eq!(path.len(), 9, "{:?}", algorithm);
+            }
+        }
+    }
+
+    #[test]
+    fn wall_follower_gives_up_on_a_goal_off_its_wall() {
+        let maze = ring(true);
+        assert_eq!(
+            run(&maze, SearchAlgorithm::WallFollower, (0, 0), (1, 1)),
+            None
+        );
+        for algorithm in [
+            SearchAlgorithm::BreadthFirst,
+            SearchAlgorithm::DepthFirst,
+            SearchAlgorithm::AStar,
+            SearchAlgorithm::Tremaux,
+        ] {
+            let path = run(&maze, algorithm, (0, 0), (1, 1)).unwrap();
+            assert_walkable(&maze, &path, (0, 0), (1, 1));
+        }
+    }
+
+    #[test]
+    fn no_path_to_a_closed_cell() {
+        let maze = ring(false);
+        for algorithm in SearchAlgorithm::ALL {
+            assert_eq!(
+                run(&maze, algorithm, (0, 0), (1, 1)),
+                None,
+                "{:?}",
+                algorithm
+            );
+        }
+    }
+}
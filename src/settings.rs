@@ -0,0 +1,710 @@
+/// The options changed on the device, kept by `SettingsStore`, and the screen to change them
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::Rectangle,
+    text::Alignment,
+    Drawable,
+};
+use log::info;
+
+use crate::{
+    challenge::ChallengeMode,
+    enemy::Behavior,
+    fog::Visibility,
+    gt911::{Orientation, TouchPoint},
+    idle::MAX_BRIGHTNESS,
+    mask::MaskShape,
+    maze_painter::CLICK_APPROXIMATION,
+    text::{Label, TextStyle, VerticalAlignment},
+    theme::Theme,
+    widget::{Dialog, Ui, UiEvent, Widget, WidgetKind, WidgetStyle},
+};
+
+/// Bumped when the stored layout changes, older settings are then ignored
+const VERSION: u8 = 2;
+/// A wrong touch orientation makes the screen impossible to use, so it's reverted
+/// unless confirmed in time
+const ORIENTATION_CONFIRM: Duration = Duration::from_secs(10);
+const THEME_ROW_HEIGHT: u32 = 40;
+/// Any darker and the screen is too hard to read to turn it up again
+const MIN_BRIGHTNESS: u8 = 10;
+
+/// The panel is mounted so only these map the touches onto the 800x480 screen
+const ORIENTATIONS: [Orientation; 2] = [Orientation::InvertedPortrait, Orientation::Portrait];
+
+/// The challenges to choose from with their names, free play first
+pub const CHALLENGES: [(&str, Option<ChallengeMode>); 4] = [
+    ("Off", None),
+    (
+        "Countdown",
+        Some(ChallengeMode::Countdown {
+            width: 20,
+            height: 12,
+            limit: Duration::from_secs(60),
+        }),
+    ),
+    (
+        "Survival",
+        Some(ChallengeMode::Survival {
+            width: 10,
+            height: 6,
+            growth: 4,
+            max_width: 38,
+            max_height: 22,
+            initial_time: Duration::from_secs(60),
+            bonus_per_level: Duration::from_secs(20),
+        }),
+    ),
+    (
+        "Time attack",
+        Some(ChallengeMode::TimeAttack {
+            width: 20,
+            height: 12,
+        }),
+    ),
+];
+
+/// The enemies hunting the token in the runner mode, no enemies first
+pub const ENEMIES: [(&str, &[Behavior]); 5] = [
+    ("None", &[]),
+    ("Walker", &[Behavior::RandomWalker]),
+    ("Wall follower", &[Behavior::WallFollower]),
+    ("Chaser", &[Behavior::Chaser]),
+    (
+        "All",
+        &[
+            Behavior::RandomWalker,
+            Behavior::WallFollower,
+            Behavior::Chaser,
+        ],
+    ),
+];
+
+/// How much of the maze is hidden, the whole maze is shown first
+pub const FOGS: [(&str, Option<Visibility>); 3] = [
+    ("Off", None),
+    ("Radius 3", Some(Visibility::Radius(3))),
+    ("Line of sight", Some(Visibility::LineOfSight)),
+];
+
+/// A plus sign, 12x9 cells with each row padded to two bytes
+const CROSS: [u8; 18] = [
+    0x0F, 0x00, 0x0F, 0x00, 0x0F, 0x00, 0xFF, 0xF0, 0xFF, 0xF0, 0xFF, 0xF0, 0x0F, 0x00, 0x0F, 0x00,
+    0x0F, 0x00,
+];
+
+/// The shapes of the maze, the whole rectangle first. Each one is in one piece, so weave
+/// mazes reach all of it too.
+pub const SHAPES: [(&str, Option<MaskShape>); 5] = [
+    ("Rectangle", None),
+    ("Circle", Some(MaskShape::Circle)),
+    ("Letter H", Some(MaskShape::Text("H"))),
+    (
+        "Heart",
+        Some(MaskShape::Ascii(
+            ".###...###.\n\
+             #####.#####\n\
+             ###########\n\
+             ###########\n\
+             .#########.\n\
+             ..#######..\n\
+             ...#####...\n\
+             ....###....\n\
+             .....#.....",
+        )),
+    ),
+    ("Cross", Some(MaskShape::Bitmap(&CROSS, 12, 9))),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    /// Bigger cells make smaller mazes on the same screen
+    pub fn cell_size(self) -> u32 {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Normal => 30,
+            Difficulty::Hard => 20,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Long winding corridors with few junctions
+    Backtracker,
+    /// Corridors crossing under each other
+    Weave,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 2] = [Algorithm::Backtracker, Algorithm::Weave];
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub difficulty: Difficulty,
+    pub algorithm: Algorithm,
+    /// Index in `Theme::ALL`
+    pub theme: usize,
+    /// How far from a cell, in pixels, a tap still reaches it
+    pub click_approximation: u32,
+    /// Backlight in percent while in use
+    pub brightness: u8,
+    /// There is no speaker yet, kept for the games to check
+    pub sound: bool,
+    pub orientation: Orientation,
+    /// Index in `CHALLENGES`
+    pub challenge: usize,
+    /// Index in `ENEMIES`
+    pub enemies: usize,
+    /// Index in `FOGS`
+    pub fog: usize,
+    /// Index in `SHAPES`
+    pub shape: usize,
+    /// Coins, keys, teleporters and one-way passages in the maze
+    pub items: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Hard,
+            algorithm: Algorithm::Backtracker,
+            theme: 0,
+            click_approximation: CLICK_APPROXIMATION,
+            brightness: MAX_BRIGHTNESS,
+            sound: true,
+            orientation: Orientation::InvertedPortrait,
+            challenge: 0,
+            enemies: 0,
+            fog: 0,
+            shape: 0,
+            items: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn challenge_mode(&self) -> Option<ChallengeMode> {
+        CHALLENGES[self.challenge].1
+    }
+
+    pub fn enemy_behaviors(&self) -> &'static [Behavior] {
+        ENEMIES[self.enemies].1
+    }
+
+    pub fn visibility(&self) -> Option<Visibility> {
+        FOGS[self.fog].1
+    }
+
+    pub fn mask_shape(&self) -> Option<MaskShape> {
+        SHAPES[self.shape].1
+    }
+
+    pub fn to_bytes(self) -> [u8; 13] {
+        [
+            VERSION,
+            Difficulty::ALL
+                .iter()
+                .position(|d| *d == self.difficulty)
+                .unwrap_or(0) as u8,
+            Algorithm::ALL
+                .iter()
+                .position(|a| *a == self.algorithm)
+                .unwrap_or(0) as u8,
+            self.theme as u8,
+            self.click_approximation.min(u8::MAX as u32) as u8,
+            self.brightness,
+            self.sound as u8,
+            ORIENTATIONS
+                .iter()
+                .position(|o| *o == self.orientation)
+                .unwrap_or(0) as u8,
+            self.challenge as u8,
+            self.enemies as u8,
+            self.fog as u8,
+            self.shape as u8,
+            self.items as u8,
+        ]
+    }
+
+    /// `None` if the bytes were stored by another version or are out of range
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let [version, difficulty, algorithm, theme, click_approximation, brightness, sound, orientation, challenge, enemies, fog, shape, items] =
+            *bytes
+        else {
+            return None;
+        };
+        let indexes = [
+            (theme, Theme::ALL.len()),
+            (challenge, CHALLENGES.len()),
+            (enemies, ENEMIES.len()),
+            (fog, FOGS.len()),
+            (shape, SHAPES.len()),
+        ];
+        if version != VERSION || indexes.iter().any(|&(index, len)| index as usize >= len) {
+            return None;
+        }
+        Some(Self {
+            difficulty: *Difficulty::ALL.get(difficulty as usize)?,
+            algorithm: *Algorithm::ALL.get(algorithm as usize)?,
+            theme: theme as usize,
+            click_approximation: click_approximation as u32,
+            brightness: brightness.clamp(MIN_BRIGHTNESS, MAX_BRIGHTNESS),
+            sound: sound != 0,
+            orientation: *ORIENTATIONS.get(orientation as usize)?,
+            challenge: challenge as usize,
+            enemies: enemies as usize,
+            fog: fog as usize,
+            shape: shape as usize,
+            items: items != 0,
+        })
+    }
+}
+
+/// Which setting was changed on the screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SettingsEvent {
+    Difficulty,
+    Algorithm,
+    Theme,
+    ClickApproximation,
+    Brightness,
+    Sound,
+    Orientation,
+    Challenge,
+    Enemies,
+    Fog,
+    Shape,
+    Items,
+    /// Everything went back to the defaults
+    Defaults,
+    /// Leave the screen, the settings should be saved
+    Done,
+}
+
+/// Indexes of the widgets in the `Ui`
+struct Widgets {
+    difficulty: usize,
+    algorithm: usize,
+    theme: usize,
+    orientation: usize,
+    challenge: usize,
+    enemies: usize,
+    fog: usize,
+    shape: usize,
+    items: usize,
+    click_approximation: usize,
+    brightness: usize,
+    sound: usize,
+    defaults: usize,
+    done: usize,
+}
+
+pub struct SettingsScreen<C: PixelColor> {
+    pub settings: Settings,
+    ui: Ui<C>,
+    widgets: Widgets,
+    /// The orientation to go back to if the new one is not confirmed in time
+    orientation_check: Option<(Orientation, Instant)>,
+}
+
+impl<C: PixelColor> SettingsScreen<C> {
+    pub fn new(settings: Settings, area: Rectangle, style: WidgetStyle<C>) -> Self {
+        let mut ui = Ui::new(area, style);
+        let origin = area.top_left;
+        // Three columns: the screen, the game, the device
+        let column = |x: i32, row: i32| {
+            Rectangle::new(origin + Point::new(x, 80 + row * 64), Size::new(240, 48))
+        };
+        let left = |row: i32| column(20, row);
+        let middle = |row: i32| column(280, row);
+        let right =
+            |row: i32| Rectangle::new(origin + Point::new(540, 80 + row * 72), Size::new(240, 60));
+        let widgets = Widgets {
+            difficulty: ui.add(Widget::button("", left(0))),
+            algorithm: ui.add(Widget::button("", left(1))),
+            orientation: ui.add(Widget::button("", left(2))),
+            // All the themes fit, one per row
+            theme: ui.add(Widget::list(
+                Rectangle::new(
+                    origin + Point::new(20, 80 + 3 * 64),
+                    Size::new(240, THEME_ROW_HEIGHT * Theme::ALL.len() as u32),
+                ),
+                Theme::ALL
+                    .iter()
+                    .map(|theme| theme.name.to_string())
+                    .collect(),
+                THEME_ROW_HEIGHT,
+            )),
+            challenge: ui.add(Widget::button("", middle(0))),
+            enemies: ui.add(Widget::button("", middle(1))),
+            fog: ui.add(Widget::button("", middle(2))),
+            shape: ui.add(Widget::button("", middle(3))),
+            items: ui.add(Widget::toggle("Items", middle(4), settings.items)),
+            click_approximation: ui.add(Widget::slider(
+                "Touch radius",
+                right(0),
+                0,
+                40,
+                settings.click_approximation as i32,
+            )),
+            brightness: ui.add(Widget::slider(
+                "Brightness",
+                right(1),
+                MIN_BRIGHTNESS as i32,
+                MAX_BRIGHTNESS as i32,
+                settings.brightness as i32,
+            )),
+            sound: ui.add(Widget::toggle(
+                // Shown so the setting is kept, nothing plays any sound yet
+                "Sound (no speaker)",
+                Rectangle::new(origin + Point::new(540, 80 + 2 * 72), Size::new(240, 48)),
+                settings.sound,
+            )),
+            defaults: ui.add(Widget::button(
+                "Defaults",
+                Rectangle::new(origin + Point::new(540, 390), Size::new(115, 50)),
+            )),
+            done: ui.add(Widget::button(
+                "Done",
+                Rectangle::new(origin + Point::new(665, 390), Size::new(115, 50)),
+            )),
+        };
+        let mut screen = Self {
+            settings,
+            ui,
+            widgets,
+            orientation_check: None,
+        };
+        screen.update_widgets();
+        screen
+    }
+
+    /// New colors, e.g. after the theme was changed
+    pub fn set_style(&mut self, style: WidgetStyle<C>) {
+        self.ui.style = style;
+        self.ui.mark_dirty(self.ui.area);
+    }
+
+    fn update_labels(&mut self) {
+        let settings = self.settings;
+        let labels = [
+            (
+                self.widgets.difficulty,
+                format!("Difficulty: {:?}", settings.difficulty),
+            ),
+            (
+                self.widgets.algorithm,
+                format!("Algorithm: {:?}", settings.algorithm),
+            ),
+            (
+                self.widgets.orientation,
+                format!("Touch: {:?}", settings.orientation),
+            ),
+            (
+                self.widgets.challenge,
+                format!("Challenge: {}", CHALLENGES[settings.challenge].0),
+            ),
+            (
+                self.widgets.enemies,
+                format!("Enemies: {}", ENEMIES[settings.enemies].0),
+            ),
+            (self.widgets.fog, format!("Fog: {}", FOGS[settings.fog].0)),
+            (
+                self.widgets.shape,
+                format!("Shape: {}", SHAPES[settings.shape].0),
+            ),
+        ];
+        for (index, label) in labels {
+            if self.ui.widget(index).label != label {
+                self.ui.widget_mut(index).label = label;
+            }
+        }
+    }
+
+    /// Set all the widgets to the settings, e.g. after going back to the defaults
+    fn update_widgets(&mut self) {
+        let settings = self.settings;
+        let values = [
+            (
+                self.widgets.click_approximation,
+                settings.click_approximation as i32,
+            ),
+            (self.widgets.brightness, settings.brightness as i32),
+        ];
+        for (index, new_value) in values {
+            if let WidgetKind::Slider { value, .. } = &mut self.ui.widget_mut(index).kind {
+                *value = new_value;
+            }
+        }
+        let toggles = [
+            (self.widgets.sound, settings.sound),
+            (self.widgets.items, settings.items),
+        ];
+        for (index, new_on) in toggles {
+            if let WidgetKind::Toggle { on } = &mut self.ui.widget_mut(index).kind {
+                *on = new_on;
+            }
+        }
+        if let WidgetKind::List { selected, .. } = &mut self.ui.widget_mut(self.widgets.theme).kind
+        {
+            *selected = Some(settings.theme);
+        }
+        self.update_labels();
+    }
+
+    /// Feed the touch samples, returns the setting that was changed if any.
+    /// Also reverts an unconfirmed touch orientation once its time is up.
+    pub fn on_touch(&mut self, touch: Option<TouchPoint>, now: Instant) -> Option<SettingsEvent> {
+        if let Some((previous, deadline)) = self.orientation_check {
+            if now > deadline {
+                info!("Touch orientation not confirmed, back to {:?}", previous);
+                self.ui.close_dialog();
+                return self.revert_orientation(previous);
+            }
+        }
+        let event = match self.ui.on_touch(touch, now)? {
+            UiEvent::Pressed(index) => self.on_pressed(index, now),
+            UiEvent::Changed(index, value) if index == self.widgets.click_approximation => {
+                self.settings.click_approximation = value as u32;
+                Some(SettingsEvent::ClickApproximation)
+            }
+            UiEvent::Changed(index, value) if index == self.widgets.brightness => {
+                self.settings.brightness = value as u8;
+                Some(SettingsEvent::Brightness)
+            }
+            UiEvent::Selected(index, item) if index == self.widgets.theme => {
+                self.settings.theme = item;
+                Some(SettingsEvent::Theme)
+            }
+            UiEvent::Toggled(index, on) if index == self.widgets.sound => {
+                self.settings.sound = on;
+                Some(SettingsEvent::Sound)
+            }
+            UiEvent::Toggled(index, on) if index == self.widgets.items => {
+                self.settings.items = on;
+                Some(SettingsEvent::Items)
+            }
+            UiEvent::DialogClosed(button) => match self.orientation_check.take() {
+                // Revert, the first button
+                Some((previous, _)) if button == 0 => self.revert_orientation(previous),
+                Some(_) => None,
+                // The defaults dialog: cancel, reset
+                None if button == 1 => {
+                    self.settings = Settings::default();
+                    self.update_widgets();
+                    Some(SettingsEvent::Defaults)
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        self.update_labels();
+        event
+    }
+
+    fn revert_orientation(&mut self, previous: Orientation) -> Option<SettingsEvent> {
+        self.orientation_check = None;
+        self.settings.orientation = previous;
+        self.update_labels();
+        Some(SettingsEvent::Orientation)
+    }
+
+    fn on_pressed(&mut self, index: usize, now: Instant) -> Option<SettingsEvent> {
+        let widgets = &self.widgets;
+        let settings = &mut self.settings;
+        if index == widgets.difficulty {
+            settings.difficulty = next(&Difficulty::ALL, settings.difficulty);
+            Some(SettingsEvent::Difficulty)
+        } else if index == widgets.algorithm {
+            settings.algorithm = next(&Algorithm::ALL, settings.algorithm);
+            Some(SettingsEvent::Algorithm)
+        } else if index == widgets.challenge {
+            settings.challenge = (settings.challenge + 1) % CHALLENGES.len();
+            Some(SettingsEvent::Challenge)
+        } else if index == widgets.enemies {
+            settings.enemies = (settings.enemies + 1) % ENEMIES.len();
+            Some(SettingsEvent::Enemies)
+        } else if index == widgets.fog {
+            settings.fog = (settings.fog + 1) % FOGS.len();
+            Some(SettingsEvent::Fog)
+        } else if index == widgets.shape {
+            settings.shape = (settings.shape + 1) % SHAPES.len();
+            Some(SettingsEvent::Shape)
+        } else if index == widgets.orientation {
+            let previous = settings.orientation;
+            settings.orientation = next(&ORIENTATIONS, previous);
+            self.orientation_check = Some((previous, now + ORIENTATION_CONFIRM));
+            self.ui.open_dialog(Dialog {
+                title: "Touch orientation".to_string(),
+                message: format!(
+                    "Keep it? It goes back by itself in {} seconds.",
+                    ORIENTATION_CONFIRM.as_secs()
+                ),
+                buttons: vec!["Revert".to_string(), "Keep".to_string()],
+            });
+            Some(SettingsEvent::Orientation)
+        } else if index == widgets.defaults {
+            self.ui.open_dialog(Dialog {
+                title: "Defaults".to_string(),
+                message: "Set everything back to the defaults?".to_string(),
+                buttons: vec!["Cancel".to_string(), "Reset".to_string()],
+            });
+            None
+        } else if index == widgets.done {
+            Some(SettingsEvent::Done)
+        } else {
+            None
+        }
+    }
+
+    /// Draw the whole screen
+    pub fn draw<D>(&mut self, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.ui.draw(target);
+        self.draw_title(target);
+    }
+
+    /// Draw what changed since the last time, returns `true` if anything was drawn
+    pub fn update<D>(&mut self, target: &mut D) -> bool
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // Clearing the widgets around the title clears it too
+        let title = self.title_area();
+        let title_dirty = self
+            .ui
+            .dirty()
+            .iter()
+            .any(|area| !area.intersection(&title).is_zero_sized());
+        let dirty = self.ui.draw_dirty(target);
+        if title_dirty {
+            self.draw_title(target);
+        }
+        !dirty.is_empty()
+    }
+
+    fn title_area(&self) -> Rectangle {
+        Rectangle::new(
+            self.ui.area.top_left + Point::new(40, 16),
+            Size::new(self.ui.area.size.width - 80, 48),
+        )
+    }
+
+    fn draw_title<D>(&self, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let style = TextStyle::title(self.ui.style.foreground)
+            .align(Alignment::Left, VerticalAlignment::Middle);
+        Label::new("Settings", self.title_area(), style)
+            .draw(target)
+            .ok();
+    }
+}
+
+/// The value after `current`, going around
+fn next<T: Copy + PartialEq>(all: &[T], current: T) -> T {
+    let index = all.iter().position(|value| *value == current).unwrap_or(0);
+    all[(index + 1) % all.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{maze::Maze, solver, weave};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn bytes_round_trip() {
+        let settings = Settings {
+            difficulty: Difficulty::Easy,
+            algorithm: Algorithm::Weave,
+            theme: Theme::ALL.len() - 1,
+            click_approximation: 12,
+            brightness: 40,
+            sound: false,
+            orientation: Orientation::Portrait,
+            challenge: CHALLENGES.len() - 1,
+            enemies: 2,
+            fog: 1,
+            shape: SHAPES.len() - 1,
+            items: true,
+        };
+        assert_eq!(Settings::from_bytes(&settings.to_bytes()), Some(settings));
+        let defaults = Settings::default();
+        assert_eq!(Settings::from_bytes(&defaults.to_bytes()), Some(defaults));
+    }
+
+    #[test]
+    fn other_versions_and_bad_values_are_ignored() {
+        let bytes = Settings::default().to_bytes();
+        let changed = |index: usize, value: u8| {
+            let mut bytes = bytes;
+            bytes[index] = value;
+            Settings::from_bytes(&bytes)
+        };
+        assert_eq!(changed(0, VERSION + 1), None);
+        assert_eq!(changed(1, Difficulty::ALL.len() as u8), None);
+        assert_eq!(changed(2, Algorithm::ALL.len() as u8), None);
+        assert_eq!(changed(3, Theme::ALL.len() as u8), None);
+        assert_eq!(changed(7, ORIENTATIONS.len() as u8), None);
+        assert_eq!(changed(8, CHALLENGES.len() as u8), None);
+        assert_eq!(changed(9, ENEMIES.len() as u8), None);
+        assert_eq!(changed(10, FOGS.len() as u8), None);
+        assert_eq!(changed(11, SHAPES.len() as u8), None);
+        assert_eq!(Settings::from_bytes(&bytes[..12]), None);
+    }
+
+    #[test]
+    fn stored_brightness_stays_readable() {
+        let mut bytes = Settings::default().to_bytes();
+        for (stored, loaded) in [(0, MIN_BRIGHTNESS), (55, 55), (255, MAX_BRIGHTNESS)] {
+            bytes[5] = stored;
+            assert_eq!(Settings::from_bytes(&bytes).unwrap().brightness, loaded);
+        }
+    }
+
+    #[test]
+    fn every_shape_is_one_weave_maze() {
+        // The smallest and the biggest maze on the screen
+        for (width, height) in [(15, 11), (38, 22)] {
+            for (name, shape) in SHAPES {
+                let mask = match shape {
+                    Some(shape) => shape.mask(width, height),
+                    None => continue,
+                };
+                let mut maze = Maze::new(width, height);
+                maze.apply_mask(&mask);
+                weave::generate_weave(&mut maze, 0.3, &mut StdRng::seed_from_u64(1));
+                // The crossings are passed through, they are never a stop
+                let stops = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| (x, y)))
+                    .filter(|&(x, y)| mask.get(x, y) && maze[(x, y)].crossing.is_none())
+                    .count();
+                let start = maze.first_active_cell().unwrap();
+                assert_eq!(
+                    solver::reachable(&maze, start, 0).len(),
+                    stops,
+                    "{} in {}x{}",
+                    name,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+}
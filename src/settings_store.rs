@@ -0,0 +1,41 @@
+/// Keeps the settings in the NVS flash partition
+use esp_idf_svc::{
+    nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault},
+    sys::EspError,
+};
+use log::info;
+
+use crate::settings::Settings;
+
+const NAMESPACE: &str = "settings";
+const KEY: &str = "settings";
+
+/// The settings in the NVS partition, so they survive a restart
+pub struct SettingsStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl SettingsStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self, EspError> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NAMESPACE, true)?,
+        })
+    }
+
+    /// The stored settings, or the defaults if there are none yet
+    pub fn load(&self) -> Settings {
+        let mut buffer = [0; 8];
+        match self.nvs.get_blob(KEY, &mut buffer) {
+            Ok(Some(bytes)) => Settings::from_bytes(bytes).unwrap_or_default(),
+            Ok(None) => Settings::default(),
+            Err(err) => {
+                info!("Can't read the settings: {:?}", err);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&mut self, settings: &Settings) -> Result<(), EspError> {
+        self.nvs.set_blob(KEY, &settings.to_bytes())
+    }
+}
@@ -0,0 +1,298 @@
+/// Key-aware maze solver. A breadth-first search over (cell, collected keys) states,
+/// following the same rules as the player: doors need keys, one-way passages and teleporters.
+use std::collections::VecDeque;
+
+use crate::maze::{CellContent, Direction, Maze, MAX_KEYS};
+
+const KEY_STATES: usize = 1 << MAX_KEYS;
+
+/// Keys picked up when entering the cell
+fn pick_up(maze: &Maze, cell: (usize, usize), keys: u8) -> u8 {
+    match maze[cell].content {
+        CellContent::Key(key) => keys | (1 << key),
+        _ => keys,
+    }
+}
+
+/// Where the player ends up after entering the cell
+fn land(maze: &Maze, cell: (usize, usize)) -> (usize, usize) {
+    match maze[cell].content {
+        CellContent::Teleporter { to } => to,
+        _ => cell,
+    }
+}
+
+// Previous state and the teleporter entered to get here, if any
+type Step = ((usize, usize), u8, Option<(usize, usize)>);
+
+/// The cell farthest from `from` following the passages, ignoring doors and items,
+/// with its distance
+fn farthest(maze: &Maze, from: (usize, usize)) -> ((usize, usize), usize) {
+    let mut distance = vec![usize::MAX; maze.width * maze.height];
+    distance[from.1 * maze.width + from.0] = 0;
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    let mut last = (from, 0);
+    while let Some((x, y)) = queue.pop_front() {
+        let d = distance[y * maze.width + x];
+        last = ((x, y), d);
+        for direction in Direction::ALL {
+            if let Some((nx, ny)) = maze.neighbor(x, y, direction) {
+                if distance[ny * maze.width + nx] == usize::MAX {
+                    distance[ny * maze.width + nx] = d + 1;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+    last
+}
+
+/// The two ends of the longest path in the maze, found with two searches:
+/// the cell farthest from any cell is one end, the cell farthest from it is the other one.
+/// Only exact for mazes without loops.
+pub fn longest_path(maze: &Maze) -> ((usize, usize), (usize, usize)) {
+    let (a, _) = farthest(maze, maze.first_active_cell().unwrap_or((0, 0)));
+    let (b, _) = farthest(maze, a);
+    (a, b)
+}
+
+/// Shortest path from `start` to `goal`, including both ends.
+/// When a teleporter is used, both the teleporter and the other end are in the path.
+pub fn solve(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+) -> Option<Vec<(usize, usize)>> {
+    let index = |cell: (usize, usize), keys: u8| {
+        (cell.1 * maze.width + cell.0) * KEY_STATES + keys as usize
+    };
+    let mut previous: Vec<Option<Step>> = vec![None; maze.width * maze.height * KEY_STATES];
+    let mut seen = vec![false; maze.width * maze.height * KEY_STATES];
+
+    let start_keys = pick_up(maze, start, 0);
+    let mut queue = VecDeque::new();
+    queue.push_back((start, start_keys));
+    seen[index(start, start_keys)] = true;
+
+    while let Some((cell, keys)) = queue.pop_front() {
+        if cell == goal {
+            // Walk back to the start
+            let mut path = vec![cell];
+            let mut state = (cell, keys);
+            while let Some((prev_cell, prev_keys, via)) = previous[index(state.0, state.1)] {
+                if let Some(via) = via {
+                    path.push(via);
+                }
+                path.push(prev_cell);
+                state = (prev_cell, prev_keys);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for direction in Direction::ALL {
+            let Some(next) = maze.can_move(cell.0, cell.1, direction, keys) else {
+                continue;
+            };
+            let next_keys = pick_up(maze, next, keys);
+            let landed = land(maze, next);
+            let via = (landed != next).then_some(next);
+            let i = index(landed, next_keys);
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            previous[i] = Some((cell, keys, via));
+            queue.push_back((landed, next_keys));
+        }
+    }
+    None
+}
+
+/// Whether the goal can still be reached from every state the player can get into from `start`,
+/// e.g. a one-way passage or a teleporter must not leave the player behind without a needed key
+pub fn always_solvable(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> bool {
+    let index = |cell: (usize, usize), keys: u8| {
+        (cell.1 * maze.width + cell.0) * KEY_STATES + keys as usize
+    };
+    let mut seen = vec![false; maze.width * maze.height * KEY_STATES];
+    // Moves between the reachable states as (to, from)
+    let mut moves = Vec::new();
+    let mut reached = Vec::new();
+    let mut goals = Vec::new();
+
+    let start_keys = pick_up(maze, start, 0);
+    let mut queue = VecDeque::new();
+    queue.push_back((start, start_keys));
+    seen[index(start, start_keys)] = true;
+    while let Some((cell, keys)) = queue.pop_front() {
+        let i = index(cell, keys);
+        reached.push(i);
+        // The game is over at the goal
+        if cell == goal {
+            goals.push(i);
+            continue;
+        }
+        for direction in Direction::ALL {
+            let Some(next) = maze.can_move(cell.0, cell.1, direction, keys) else {
+                continue;
+            };
+            let next_keys = pick_up(maze, next, keys);
+            let landed = land(maze, next);
+            let j = index(landed, next_keys);
+            moves.push((j, i));
+            if !seen[j] {
+                seen[j] = true;
+                queue.push_back((landed, next_keys));
+            }
+        }
+    }
+
+    // Walk the moves backwards from the goal, every reached state must be found
+    moves.sort_unstable();
+    let mut solvable = vec![false; seen.len()];
+    for &i in &goals {
+        solvable[i] = true;
+    }
+    let mut stack = goals;
+    while let Some(to) = stack.pop() {
+        let first = moves.partition_point(|&(t, _)| t < to);
+        for &(t, from) in &moves[first..] {
+            if t != to {
+                break;
+            }
+            if !solvable[from] {
+                solvable[from] = true;
+                stack.push(from);
+            }
+        }
+    }
+    reached.iter().all(|&i| solvable[i])
+}
+
+/// Cells reachable from `start` with the given keys, without picking up new ones
+pub fn reachable(maze: &Maze, start: (usize, usize), keys: u8) -> Vec<(usize, usize)> {
+    let mut seen = vec![false; maze.width * maze.height];
+    let mut cells = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    seen[start.1 * maze.width + start.0] = true;
+    while let Some(cell) = queue.pop_front() {
+        cells.push(cell);
+        for direction in Direction::ALL {
+            let Some(next) = maze.can_move(cell.0, cell.1, direction, keys) else {
+                continue;
+            };
+            let next = land(maze, next);
+            if !seen[next.1 * maze.width + next.0] {
+                seen[next.1 * maze.width + next.0] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A corridor along the top row, from (0, 0) to (width - 1, 0), with a closed row below it
+    fn corridor(width: usize) -> Maze {
+        let mut maze = Maze::new(width, 2);
+        for x in 1..width {
+            maze.carve((x - 1, 0), (x, 0));
+        }
+        maze
+    }
+
+    #[test]
+    fn solves_a_generated_maze() {
+        let mut maze = Maze::new(12, 8);
+        maze.generate_seeded(0, 0, 3);
+        let path = solve(&maze, (0, 0), (11, 7)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(11, 7)));
+        for step in path.windows(2) {
+            assert!(maze.open_neighbors(step[0].0, step[0].1).contains(&step[1]));
+        }
+        assert!(always_solvable(&maze, (0, 0), (11, 7)));
+    }
+
+    #[test]
+    fn a_door_needs_its_key() {
+        // The key is in a side branch below the corridor, behind the start
+        let mut maze = corridor(5);
+        maze.carve((0, 0), (0, 1));
+        maze[(2, 0)].content = CellContent::Door(1);
+        assert!(solve(&maze, (1, 0), (4, 0)).is_none());
+        assert_eq!(reachable(&maze, (1, 0), 0).len(), 3);
+
+        maze[(0, 1)].content = CellContent::Key(1);
+        let path = solve(&maze, (1, 0), (4, 0)).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                (1, 0),
+                (0, 0),
+                (0, 1),
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (4, 0)
+            ]
+        );
+        assert_eq!(reachable(&maze, (1, 0), 1 << 1).len(), 6);
+        assert!(always_solvable(&maze, (1, 0), (4, 0)));
+    }
+
+    #[test]
+    fn one_way_passages_are_only_walked_forward() {
+        let mut maze = corridor(4);
+        maze[(1, 0)].one_way = Some(Direction::East);
+        assert!(solve(&maze, (0, 0), (3, 0)).is_some());
+        assert!(solve(&maze, (3, 0), (0, 0)).is_none());
+    }
+
+    #[test]
+    fn a_one_way_passage_can_leave_a_key_behind() {
+        // Start in the middle, the key is to the west and the door to the east,
+        // going east first through the one-way passage makes the key unreachable
+        let mut maze = corridor(6);
+        maze[(0, 0)].content = CellContent::Key(0);
+        maze[(2, 0)].one_way = Some(Direction::East);
+        maze[(4, 0)].content = CellContent::Door(0);
+        assert!(solve(&maze, (2, 0), (5, 0)).is_some());
+        assert!(!always_solvable(&maze, (2, 0), (5, 0)));
+
+        // Fine once the key is on the way
+        maze[(0, 0)].content = CellContent::Empty;
+        maze[(3, 0)].content = CellContent::Key(0);
+        assert!(always_solvable(&maze, (2, 0), (5, 0)));
+    }
+
+    #[test]
+    fn teleporters_are_part_of_the_path() {
+        // Two corridors with no passage between them
+        let mut maze = Maze::new(3, 2);
+        maze.carve((0, 0), (1, 0));
+        maze.carve((0, 1), (1, 1));
+        maze.carve((1, 1), (2, 1));
+        assert!(solve(&maze, (0, 0), (2, 1)).is_none());
+        maze[(1, 0)].content = CellContent::Teleporter { to: (0, 1) };
+        maze[(0, 1)].content = CellContent::Teleporter { to: (1, 0) };
+        assert_eq!(
+            solve(&maze, (0, 0), (2, 1)).unwrap(),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 1)]
+        );
+        // Walking back onto the teleporter goes back to the first corridor, still solvable
+        assert!(always_solvable(&maze, (0, 0), (2, 1)));
+    }
+
+    #[test]
+    fn longest_path_of_a_corridor_is_its_ends() {
+        let (a, b) = longest_path(&corridor(7));
+        assert!((a, b) == ((0, 0), (6, 0)) || (a, b) == ((6, 0), (0, 0)));
+    }
+}
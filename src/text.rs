@@ -0,0 +1,392 @@
+/// Text for the screens and the HUD: fonts, alignment, word wrapping and labels
+use embedded_graphics::{
+    mono_font::{
+        ascii::{FONT_10X20, FONT_8X13},
+        MonoFont, MonoTextStyle,
+    },
+    prelude::{DrawTarget, Pixel, PixelColor, Point, Size},
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text},
+    Drawable,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    Top,
+    Middle,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle<C: PixelColor> {
+    pub font: &'static MonoFont<'static>,
+    /// Every pixel of the font drawn as a square of this size, for titles
+    pub scale: u32,
+    pub color: C,
+    /// Fills the whole area of a label
+    pub background: Option<C>,
+    pub alignment: Alignment,
+    pub vertical_alignment: VerticalAlignment,
+    /// Extra pixels between the lines, before scaling
+    pub line_spacing: u32,
+}
+
+impl<C: PixelColor> TextStyle<C> {
+    /// 8x13 pixels, for menus and lists
+    pub fn medium(color: C) -> Self {
+        Self::new(&FONT_8X13, color)
+    }
+
+    /// 10x20 pixels, for buttons and messages
+    pub fn large(color: C) -> Self {
+        Self::new(&FONT_10X20, color)
+    }
+
+    /// The large font scaled up, for titles
+    pub fn title(color: C) -> Self {
+        Self::large(color).scale(2)
+    }
+
+    /// Left and top aligned text in the font
+    pub fn new(font: &'static MonoFont<'static>, color: C) -> Self {
+        Self {
+            font,
+            scale: 1,
+            color,
+            background: None,
+            alignment: Alignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            line_spacing: 2,
+        }
+    }
+
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn background(mut self, background: C) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn align(mut self, alignment: Alignment, vertical_alignment: VerticalAlignment) -> Self {
+        self.alignment = alignment;
+        self.vertical_alignment = vertical_alignment;
+        self
+    }
+
+    pub fn line_height(&self) -> u32 {
+        (self.font.character_size.height + self.line_spacing) * self.scale
+    }
+
+    /// Width of a single line of text, in pixels
+    pub fn line_width(&self, line: &str) -> u32 {
+        let chars = line.chars().count() as u32;
+        if chars == 0 {
+            return 0;
+        }
+        let advance = self.font.character_size.width + self.font.character_spacing;
+        (chars * advance - self.font.character_spacing) * self.scale
+    }
+
+    /// Size of the text once wrapped to `max_width`
+    pub fn measure(&self, text: &str, max_width: u32) -> Size {
+        let lines = self.wrap(text, max_width);
+        let width = lines.iter().map(|line| self.line_width(line)).max();
+        let height = (lines.len() as u32 * self.line_height())
+            .saturating_sub(self.line_spacing * self.scale);
+        Size::new(width.unwrap_or(0), height)
+    }
+
+    /// Break the text into lines at most `max_width` wide, at the spaces where possible.
+    /// Line breaks in the text are kept, words longer than a line are cut.
+    pub fn wrap<'a>(&self, text: &'a str, max_width: u32) -> Vec<&'a str> {
+        let advance = (self.font.character_size.width + self.font.character_spacing) * self.scale;
+        let max_chars = ((max_width + self.font.character_spacing * self.scale) / advance.max(1))
+            .max(1) as usize;
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut rest = paragraph.trim_end();
+            if rest.is_empty() {
+                lines.push(rest);
+                continue;
+            }
+            while !rest.is_empty() {
+                // Byte index after `max_chars` characters, or the end of the text
+                let limit = rest
+                    .char_indices()
+                    .nth(max_chars)
+                    .map_or(rest.len(), |(i, _)| i);
+                if limit == rest.len() {
+                    lines.push(rest);
+                    break;
+                }
+                // Including the character after the limit, a space there ends the line as well
+                let window = rest
+                    .char_indices()
+                    .nth(max_chars + 1)
+                    .map_or(rest.len(), |(i, _)| i);
+                let end = match rest[..window].rfind(' ') {
+                    Some(space) if space > 0 => space,
+                    _ => limit,
+                };
+                lines.push(rest[..end].trim_end());
+                rest = rest[end..].trim_start();
+            }
+        }
+        lines
+    }
+
+    /// A single line with its top left corner at `position`
+    pub fn draw_line<D>(&self, line: &str, position: Point, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let style = MonoTextStyle::new(self.font, self.color);
+        if self.scale == 1 {
+            Text::with_baseline(line, position, style, Baseline::Top)
+                .draw(target)
+                .ok();
+            return;
+        }
+        let mut scaled = Scaled {
+            target,
+            origin: position,
+            scale: self.scale,
+        };
+        Text::with_baseline(line, position, style, Baseline::Top)
+            .draw(&mut scaled)
+            .ok();
+    }
+}
+
+/// Wrapped text in an area of the screen, the lines that don't fit are left out
+pub struct Label<'a, C: PixelColor> {
+    pub text: &'a str,
+    pub area: Rectangle,
+    pub style: TextStyle<C>,
+}
+
+impl<'a, C: PixelColor> Label<'a, C> {
+    pub fn new(text: &'a str, area: Rectangle, style: TextStyle<C>) -> Self {
+        Self { text, area, style }
+    }
+}
+
+impl<C: PixelColor> Drawable for Label<'_, C> {
+    type Color = C;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        if let Some(background) = self.style.background {
+            self.area
+                .into_styled(PrimitiveStyle::with_fill(background))
+                .draw(target)?;
+        }
+        let style = &self.style;
+        let line_height = style.line_height() as i32;
+        let lines = style.wrap(self.text, self.area.size.width);
+        let fitting = (self.area.size.height as i32
+            + style.line_spacing as i32 * style.scale as i32)
+            / line_height.max(1);
+        let lines = &lines[..lines.len().min(fitting.max(0) as usize)];
+        let height = lines.len() as i32 * line_height - (style.line_spacing * style.scale) as i32;
+        let free = self.area.size.height as i32 - height;
+        let top = self.area.top_left.y
+            + match style.vertical_alignment {
+                VerticalAlignment::Top => 0,
+                VerticalAlignment::Middle => free / 2,
+            };
+        for (i, line) in lines.iter().enumerate() {
+            let free = self.area.size.width as i32 - style.line_width(line) as i32;
+            let left = self.area.top_left.x
+                + match style.alignment {
+                    Alignment::Left => 0,
+                    Alignment::Center => free / 2,
+                    Alignment::Right => free,
+                };
+            style.draw_line(line, Point::new(left, top + i as i32 * line_height), target);
+        }
+        Ok(())
+    }
+}
+
+/// Draws every pixel as a square, growing away from `origin`
+struct Scaled<'a, D> {
+    target: &'a mut D,
+    origin: Point,
+    scale: u32,
+}
+
+impl<D: DrawTarget> embedded_graphics::prelude::Dimensions for Scaled<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        // The glyphs are clipped by the real target
+        Rectangle::new(
+            Point::new(i32::MIN / 2, i32::MIN / 2),
+            Size::new(u32::MAX / 2, u32::MAX / 2),
+        )
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Scaled<'_, D> {
+    type Color = D::Color;
+
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = Size::new(self.scale, self.scale);
+        for Pixel(point, color) in pixels {
+            let top_left = self.origin + (point - self.origin) * self.scale as i32;
+            self.target
+                .fill_solid(&Rectangle::new(top_left, size), color)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        mono_font::ascii::FONT_6X10,
+        pixelcolor::{Rgb565, RgbColor},
+        prelude::Transform,
+    };
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    /// 6x10 glyphs without spacing, 10 of them in 60 pixels
+    fn style() -> TextStyle<Rgb565> {
+        TextStyle::new(&FONT_6X10, Rgb565::WHITE)
+    }
+
+    /// The smallest rectangle around the drawn pixels
+    fn drawn(frame: &FrameBuffer) -> Option<Rectangle> {
+        let mut corners: Option<(Point, Point)> = None;
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                if frame.get_pixel(x, y) == Rgb565::BLACK {
+                    continue;
+                }
+                let point = Point::new(x as i32, y as i32);
+                corners = Some(match corners {
+                    Some((min, max)) => (min.component_min(point), max.component_max(point)),
+                    None => (point, point),
+                });
+            }
+        }
+        corners.map(|(min, max)| Rectangle::with_corners(min, max))
+    }
+
+    fn draw_label(text: &str, style: TextStyle<Rgb565>, area: Rectangle) -> Option<Rectangle> {
+        let mut frame = FrameBuffer::new(80, 60);
+        Label::new(text, area, style).draw(&mut frame).ok();
+        drawn(&frame)
+    }
+
+    #[test]
+    fn wraps_at_the_spaces() {
+        let style = style();
+        assert_eq!(
+            style.wrap("hello there world", 60),
+            ["hello", "there", "world"]
+        );
+        // A word ending right at the edge still fits
+        assert_eq!(style.wrap("abcd fghij klm", 60), ["abcd fghij", "klm"]);
+        assert_eq!(style.wrap("one\n\ntwo  ", 60), ["one", "", "two"]);
+    }
+
+    #[test]
+    fn cuts_the_words_longer_than_a_line() {
+        let style = style();
+        assert_eq!(
+            style.wrap("abcdefghijklmnopqrstuvw", 60),
+            ["abcdefghij", "klmnopqrst", "uvw"]
+        );
+        assert_eq!(style.wrap("a bcdefghijklm", 60), ["a", "bcdefghijk", "lm"]);
+    }
+
+    #[test]
+    fn empty_text_is_one_empty_line() {
+        let style = style();
+        assert_eq!(style.wrap("", 60), [""]);
+        assert_eq!(style.measure("", 60), Size::new(0, 10));
+        assert_eq!(
+            draw_label("", style, Rectangle::new(Point::zero(), Size::new(60, 40))),
+            None
+        );
+    }
+
+    #[test]
+    fn one_character_per_line_when_narrower_than_a_glyph() {
+        let style = style();
+        assert_eq!(style.wrap("abc", 3), ["a", "b", "c"]);
+        assert_eq!(style.wrap("abc", 0), ["a", "b", "c"]);
+        // Three lines with the spacing between them
+        assert_eq!(style.measure("abc", 0), Size::new(6, 34));
+    }
+
+    #[test]
+    fn scaled_text_measures_in_screen_pixels() {
+        let style = style().scale(2);
+        assert_eq!(style.line_width("ab"), 24);
+        assert_eq!(style.wrap("ab cd", 30), ["ab", "cd"]);
+        assert_eq!(style.measure("ab cd", 30), Size::new(24, 44));
+    }
+
+    #[test]
+    fn labels_are_aligned_in_their_area() {
+        // "ab" is 12x10, 48 and 30 pixels are left in the area
+        let area = Rectangle::new(Point::new(10, 10), Size::new(60, 40));
+        let aligned = |alignment, vertical_alignment| {
+            draw_label("ab", style().align(alignment, vertical_alignment), area).unwrap()
+        };
+        let top_left = aligned(Alignment::Left, VerticalAlignment::Top);
+        assert!(area.contains(top_left.top_left));
+        for (alignment, vertical_alignment, offset) in [
+            (Alignment::Center, VerticalAlignment::Top, Point::new(24, 0)),
+            (Alignment::Right, VerticalAlignment::Top, Point::new(48, 0)),
+            (
+                Alignment::Left,
+                VerticalAlignment::Middle,
+                Point::new(0, 15),
+            ),
+            (
+                Alignment::Right,
+                VerticalAlignment::Middle,
+                Point::new(48, 15),
+            ),
+        ] {
+            assert_eq!(
+                aligned(alignment, vertical_alignment),
+                top_left.translate(offset),
+                "{:?} {:?}",
+                alignment,
+                vertical_alignment
+            );
+        }
+    }
+
+    #[test]
+    fn each_line_is_aligned_and_the_rest_left_out() {
+        let area = Rectangle::new(Point::zero(), Size::new(24, 60));
+        let right = style().align(Alignment::Right, VerticalAlignment::Top);
+        // "abcd" fills the width, the second line only has "ab" on the right
+        let both = draw_label("abcd ab", right, area).unwrap();
+        let second = draw_label("\nab", right, area).unwrap();
+        assert_eq!(both.top_left.x, 0);
+        assert_eq!(second.top_left.x, 12);
+        assert_eq!(both.bottom_right(), second.bottom_right());
+        // Only the first line fits in 15 pixels
+        let first = draw_label("abcd", right, area).unwrap();
+        let short = Rectangle::new(Point::zero(), Size::new(24, 15));
+        assert_eq!(draw_label("abcd ab", right, short), Some(first));
+    }
+}
@@ -0,0 +1,118 @@
+/// Colors and wall styles of the whole screen, switchable at runtime
+use embedded_graphics::{
+    pixelcolor::{Rgb565, RgbColor},
+    prelude::PixelColor,
+    primitives::{PrimitiveStyle, PrimitiveStyleBuilder},
+};
+
+use crate::path::PathStyle;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Theme<C: PixelColor> {
+    pub name: &'static str,
+    pub background: C,
+    pub walls: C,
+    pub wall_width: u32,
+    /// Round the corners where the walls meet, only visible with thick walls
+    pub rounded_corners: bool,
+    pub start: C,
+    pub goal: C,
+    pub path: PathStyle<C>,
+    /// Fill of the explored cells, none to leave them as the background
+    pub visited: Option<C>,
+    /// Text and widgets drawn over the maze
+    pub hud: C,
+    pub hud_background: C,
+}
+
+impl<C: PixelColor> Theme<C> {
+    pub fn wall_style(&self) -> PrimitiveStyle<C> {
+        PrimitiveStyle::with_stroke(self.walls, self.wall_width)
+    }
+
+    pub fn path_style(&self) -> PathStyle<C> {
+        self.path
+    }
+
+    pub fn marker_style(&self, color: C) -> PrimitiveStyle<C> {
+        PrimitiveStyleBuilder::new()
+            .fill_color(color)
+            .stroke_color(self.walls)
+            .stroke_width(1)
+            .build()
+    }
+}
+
+// 8-bit channels to RGB565
+const fn rgb(r: u8, g: u8, b: u8) -> Rgb565 {
+    Rgb565::new(r >> 3, g >> 2, b >> 3)
+}
+
+impl Theme<Rgb565> {
+    /// White walls on black, the original look
+    pub const CLASSIC: Self = Self {
+        name: "classic",
+        background: Rgb565::BLACK,
+        walls: Rgb565::WHITE,
+        wall_width: 1,
+        rounded_corners: false,
+        start: Rgb565::YELLOW,
+        goal: Rgb565::YELLOW,
+        path: PathStyle::solid(Rgb565::GREEN, 3),
+        visited: None,
+        hud: Rgb565::WHITE,
+        hud_background: Rgb565::BLACK,
+    };
+
+    /// Dimmed colors for a dark room
+    pub const DARK: Self = Self {
+        name: "dark",
+        background: rgb(10, 12, 24),
+        walls: rgb(90, 100, 130),
+        wall_width: 2,
+        rounded_corners: true,
+        start: rgb(60, 160, 90),
+        goal: rgb(180, 60, 60),
+        path: PathStyle::gradient((60, 140, 170), (150, 90, 190), 3),
+        visited: Some(rgb(20, 26, 48)),
+        hud: rgb(150, 160, 190),
+        hud_background: rgb(20, 24, 40),
+    };
+
+    /// Thick walls and saturated colors
+    pub const HIGH_CONTRAST: Self = Self {
+        name: "high contrast",
+        background: Rgb565::BLACK,
+        walls: Rgb565::WHITE,
+        wall_width: 3,
+        rounded_corners: false,
+        start: Rgb565::GREEN,
+        goal: Rgb565::MAGENTA,
+        path: PathStyle::solid(Rgb565::YELLOW, 5).with_arrows(4),
+        visited: None,
+        hud: Rgb565::YELLOW,
+        hud_background: Rgb565::BLACK,
+    };
+
+    /// The Okabe-Ito palette, the colors stay apart with any color vision deficiency
+    pub const COLOR_BLIND_SAFE: Self = Self {
+        name: "color-blind safe",
+        background: Rgb565::BLACK,
+        walls: rgb(240, 240, 240),
+        wall_width: 2,
+        rounded_corners: true,
+        start: rgb(0, 158, 115),
+        goal: rgb(213, 94, 0),
+        path: PathStyle::gradient((86, 180, 233), (0, 114, 178), 3).with_arrows(6),
+        visited: Some(rgb(0, 50, 80)),
+        hud: rgb(240, 228, 66),
+        hud_background: Rgb565::BLACK,
+    };
+
+    pub const ALL: [Self; 4] = [
+        Self::CLASSIC,
+        Self::DARK,
+        Self::HIGH_CONTRAST,
+        Self::COLOR_BLIND_SAFE,
+    ];
+}
@@ -0,0 +1,58 @@
+/// The walls of a maze as edges of the grid, each one once, with the collinear ones merged
+use crate::maze::{Maze, MazeCell};
+
+/// A straight wall between two corners of the grid, corner `(x, y)` is the top left one of cell `(x, y)`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WallRun {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// The longest straight walls along the grid lines. A wall is there when any of the
+/// two cells next to it is shown and closed on that side, crossings have no walls.
+pub fn wall_runs(maze: &Maze, shown: impl Fn(usize, usize) -> bool) -> Vec<WallRun> {
+    let closed = |x: usize, y: usize, open: fn(&MazeCell) -> bool| {
+        let cell = &maze[(x, y)];
+        shown(x, y) && cell.crossing.is_none() && !open(cell)
+    };
+    let mut runs = Vec::new();
+    for y in 0..=maze.height {
+        let mut start = None;
+        for x in 0..=maze.width {
+            let wall = x < maze.width
+                && ((y > 0 && closed(x, y - 1, |c| c.south))
+                    || (y < maze.height && closed(x, y, |c| c.north)));
+            match (wall, start) {
+                (true, None) => start = Some(x),
+                (false, Some(from)) => {
+                    runs.push(WallRun {
+                        from: (from, y),
+                        to: (x, y),
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    for x in 0..=maze.width {
+        let mut start = None;
+        for y in 0..=maze.height {
+            let wall = y < maze.height
+                && ((x > 0 && closed(x - 1, y, |c| c.east))
+                    || (x < maze.width && closed(x, y, |c| c.west)));
+            match (wall, start) {
+                (true, None) => start = Some(y),
+                (false, Some(from)) => {
+                    runs.push(WallRun {
+                        from: (x, from),
+                        to: (x, y),
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    runs
+}
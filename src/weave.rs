@@ -0,0 +1,176 @@
+/// Weave mazes, where corridors can pass under perpendicular corridors.
+/// Generated with Kruskal's algorithm after placing the crossings first: each crossing
+/// joins the cells above and below it and the cells left and right of it in one go.
+use rand::{seq::SliceRandom, Rng};
+
+use crate::maze::{Crossing, Direction, Maze};
+
+/// Disjoint sets of cells already connected to each other
+struct Sets {
+    parent: Vec<usize>,
+}
+
+impl Sets {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Returns `false` if they were already connected
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parent[a] = b;
+        a != b
+    }
+}
+
+fn open(maze: &mut Maze, (x, y): (usize, usize), direction: Direction) {
+    let cell = &mut maze[(x, y)];
+    match direction {
+        Direction::North => cell.north = true,
+        Direction::South => cell.south = true,
+        Direction::East => cell.east = true,
+        Direction::West => cell.west = true,
+    }
+}
+
+fn is_untouched(maze: &Maze, cell: (usize, usize)) -> bool {
+    let cell = &maze[cell];
+    cell.active && !cell.north && !cell.south && !cell.east && !cell.west
+}
+
+/// Generate a weave maze, `density` is the chance of each inner cell to become a crossing.
+/// Crossings are never next to each other nor on the border.
+pub fn generate_weave<R: Rng>(maze: &mut Maze, density: f64, rng: &mut R) {
+    let width = maze.width;
+    let index = |(x, y): (usize, usize)| y * width + x;
+    let mut sets = Sets::new(maze.width * maze.height);
+
+    let mut inner: Vec<(usize, usize)> = (1..maze.height.saturating_sub(1))
+        .flat_map(|y| (1..width.saturating_sub(1)).map(move |x| (x, y)))
+        .collect();
+    inner.shuffle(rng);
+    for (x, y) in inner {
+        if !rng.gen_bool(density) {
+            continue;
+        }
+        let around = [(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)];
+        if !is_untouched(maze, (x, y)) || around.iter().any(|&cell| !is_untouched(maze, cell)) {
+            continue;
+        }
+        let mut roots: Vec<usize> = around.iter().map(|&cell| sets.find(index(cell))).collect();
+        roots.sort();
+        roots.dedup();
+        if roots.len() != around.len() {
+            continue;
+        }
+        for direction in Direction::ALL {
+            open(maze, (x, y), direction);
+        }
+        open(maze, around[0], Direction::South);
+        open(maze, around[1], Direction::North);
+        open(maze, around[2], Direction::East);
+        open(maze, around[3], Direction::West);
+        maze[(x, y)].crossing = Some(if rng.gen_bool(0.5) {
+            Crossing::NorthSouthOver
+        } else {
+            Crossing::EastWestOver
+        });
+        sets.union(index(around[0]), index(around[1]));
+        sets.union(index(around[2]), index(around[3]));
+    }
+
+    let mut edges: Vec<((usize, usize), Direction)> = (0..maze.height)
+        .flat_map(|y| {
+            (0..width).flat_map(move |x| [((x, y), Direction::East), ((x, y), Direction::South)])
+        })
+        .filter(|&((x, y), direction)| match direction {
+            Direction::East => x + 1 < width,
+            _ => y + 1 < maze.height,
+        })
+        .collect();
+    edges.shuffle(rng);
+    for ((x, y), direction) in edges {
+        let (dx, dy) = direction.offset();
+        let next = ((x as i32 + dx) as usize, (y as i32 + dy) as usize);
+        let (a, b) = (&maze[(x, y)], &maze[next]);
+        if !a.active || !b.active || a.crossing.is_some() || b.crossing.is_some() {
+            continue;
+        }
+        if sets.union(index((x, y)), index(next)) {
+            open(maze, (x, y), direction);
+            open(maze, next, direction.opposite());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::solver;
+
+    fn weave(seed: u64) -> Maze {
+        let mut maze = Maze::new(15, 10);
+        generate_weave(&mut maze, 0.4, &mut StdRng::seed_from_u64(seed));
+        maze
+    }
+
+    fn crossings(maze: &Maze) -> Vec<(usize, usize)> {
+        (0..maze.height)
+            .flat_map(|y| (0..maze.width).map(move |x| (x, y)))
+            .filter(|&cell| maze[cell].crossing.is_some())
+            .collect()
+    }
+
+    #[test]
+    fn every_cell_is_reachable() {
+        for seed in 0..5 {
+            let maze = weave(seed);
+            let crossings = crossings(&maze);
+            assert!(!crossings.is_empty());
+            // The crossings are passed through, they are never a stop
+            let reachable = solver::reachable(&maze, (0, 0), 0);
+            assert_eq!(reachable.len(), 15 * 10 - crossings.len());
+        }
+    }
+
+    #[test]
+    fn crossings_are_apart_and_off_the_border() {
+        for seed in 0..5 {
+            let maze = weave(seed);
+            for (x, y) in crossings(&maze) {
+                assert!(x > 0 && y > 0 && x < maze.width - 1 && y < maze.height - 1);
+                for cell in [(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)] {
+                    assert!(maze[cell].crossing.is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solver_passes_under_the_crossings() {
+        let maze = weave(1);
+        for (x, y) in crossings(&maze) {
+            assert_eq!(
+                solver::solve(&maze, (x, y - 1), (x, y + 1)),
+                Some(vec![(x, y - 1), (x, y + 1)])
+            );
+            assert_eq!(
+                solver::solve(&maze, (x + 1, y), (x - 1, y)),
+                Some(vec![(x + 1, y), (x - 1, y)])
+            );
+        }
+    }
+}
@@ -0,0 +1,893 @@
+/// Retained-mode widgets for the menus: buttons, toggles, sliders, scrolling lists and
+/// modal dialogs, driven by the touches of the GT911.
+///
+/// The widgets keep their state, a touch changing anything marks the area as dirty so only
+/// that part of the screen gets drawn again.
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::{DrawTarget, PixelColor, Point, Size},
+    primitives::{
+        Circle, CornerRadii, Line, Primitive, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle,
+        RoundedRectangle,
+    },
+    text::Alignment,
+    Drawable,
+};
+
+use crate::{
+    gt911::TouchPoint,
+    text::{Label, TextStyle, VerticalAlignment},
+    theme::Theme,
+};
+
+/// Vertical movement in pixels before a touch on a list scrolls it instead of selecting
+const SCROLL_THRESHOLD: i32 = 8;
+
+#[derive(Copy, Clone, Debug)]
+pub struct WidgetStyle<C: PixelColor> {
+    pub background: C,
+    pub foreground: C,
+    /// Pressed buttons, switched on toggles, filled part of the sliders, selected items
+    pub accent: C,
+    pub text: TextStyle<C>,
+}
+
+impl WidgetStyle<Rgb565> {
+    /// The HUD colors of the theme, with its goal color as the accent
+    pub fn from_theme(theme: &Theme<Rgb565>) -> Self {
+        Self {
+            background: theme.hud_background,
+            foreground: theme.hud,
+            accent: theme.goal,
+            text: TextStyle::medium(theme.hud),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WidgetKind {
+    Button,
+    Toggle {
+        on: bool,
+    },
+    Slider {
+        value: i32,
+        min: i32,
+        max: i32,
+    },
+    /// Items of `item_height` pixels, `scroll` is the first one shown
+    List {
+        items: Vec<String>,
+        item_height: u32,
+        selected: Option<usize>,
+        scroll: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Widget {
+    pub label: String,
+    pub area: Rectangle,
+    pub kind: WidgetKind,
+    /// Disabled widgets are shown but ignore the touches
+    pub enabled: bool,
+}
+
+/// What a touch did, the widgets are identified by the index `Ui::add` returned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UiEvent {
+    Pressed(usize),
+    Toggled(usize, bool),
+    Changed(usize, i32),
+    Selected(usize, usize),
+    /// The dialog was closed with the button of that index
+    DialogClosed(usize),
+}
+
+/// A box over the widgets taking all the touches until one of its buttons is pressed
+#[derive(Clone, Debug)]
+pub struct Dialog {
+    pub title: String,
+    pub message: String,
+    pub buttons: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Target {
+    Widget(usize),
+    DialogButton(usize),
+}
+
+/// The widget or dialog button under the finger since the touch started
+#[derive(Copy, Clone, Debug)]
+struct Active {
+    target: Target,
+    start: Point,
+    last: Point,
+    last_seen: Instant,
+    start_scroll: usize,
+    scrolled: bool,
+}
+
+pub struct Ui<C: PixelColor> {
+    /// The part of the screen owned by the widgets, cleared with the background
+    pub area: Rectangle,
+    pub style: WidgetStyle<C>,
+    widgets: Vec<Widget>,
+    dialog: Option<(Dialog, Rectangle)>,
+    active: Option<Active>,
+    dirty: Vec<Rectangle>,
+    // How long without a touch sample before the finger counts as lifted
+    release_timeout: Duration,
+}
+
+impl Widget {
+    pub fn button(label: &str, area: Rectangle) -> Self {
+        Self::new(label, area, WidgetKind::Button)
+    }
+
+    pub fn toggle(label: &str, area: Rectangle, on: bool) -> Self {
+        Self::new(label, area, WidgetKind::Toggle { on })
+    }
+
+    pub fn slider(label: &str, area: Rectangle, min: i32, max: i32, value: i32) -> Self {
+        let value = value.clamp(min, max);
+        Self::new(label, area, WidgetKind::Slider { value, min, max })
+    }
+
+    pub fn list(area: Rectangle, items: Vec<String>, item_height: u32) -> Self {
+        let kind = WidgetKind::List {
+            items,
+            item_height: item_height.max(1),
+            selected: None,
+            scroll: 0,
+        };
+        Self::new("", area, kind)
+    }
+
+    fn new(label: &str, area: Rectangle, kind: WidgetKind) -> Self {
+        Self {
+            label: label.to_string(),
+            area,
+            kind,
+            enabled: true,
+        }
+    }
+
+    /// Number of list items fitting in the area
+    fn visible_items(&self) -> usize {
+        match &self.kind {
+            WidgetKind::List { item_height, .. } => (self.area.size.height / item_height) as usize,
+            _ => 0,
+        }
+    }
+
+    /// The track of a slider, in the lower half of the area
+    fn slider_track(&self) -> (Point, Point) {
+        let area = self.area;
+        let margin = area.size.height as i32 / 4;
+        let y = area.top_left.y + area.size.height as i32 * 3 / 4;
+        (
+            Point::new(area.top_left.x + margin, y),
+            Point::new(area.top_left.x + area.size.width as i32 - 1 - margin, y),
+        )
+    }
+
+    fn slider_value_at(&self, x: i32) -> Option<i32> {
+        let WidgetKind::Slider { min, max, .. } = self.kind else {
+            return None;
+        };
+        let (start, end) = self.slider_track();
+        let length = (end.x - start.x).max(1);
+        let offset = (x - start.x).clamp(0, length);
+        // Rounded to the closest value
+        Some(min + ((max - min) * offset + length / 2) / length)
+    }
+
+    fn draw<C, D>(&self, style: &WidgetStyle<C>, pressed: bool, target: &mut D)
+    where
+        C: PixelColor,
+        D: DrawTarget<Color = C>,
+    {
+        let foreground = style.foreground;
+        let text = TextStyle {
+            color: foreground,
+            ..style.text
+        };
+        self.area
+            .into_styled(PrimitiveStyle::with_fill(style.background))
+            .draw(target)
+            .ok();
+        match &self.kind {
+            WidgetKind::Button => {
+                let fill = if pressed {
+                    style.accent
+                } else {
+                    style.background
+                };
+                RoundedRectangle::new(self.area, CornerRadii::new(Size::new(6, 6)))
+                    .into_styled(
+                        PrimitiveStyleBuilder::new()
+                            .fill_color(fill)
+                            .stroke_color(foreground)
+                            .stroke_width(2)
+                            .build(),
+                    )
+                    .draw(target)
+                    .ok();
+                let text = text.align(Alignment::Center, VerticalAlignment::Middle);
+                Label::new(&self.label, self.area, text).draw(target).ok();
+            }
+            WidgetKind::Toggle { on } => {
+                let height = self.area.size.height.min(30);
+                let switch = Rectangle::new(
+                    Point::new(
+                        self.area.top_left.x + self.area.size.width as i32 - 2 * height as i32,
+                        self.area.center().y - height as i32 / 2,
+                    ),
+                    Size::new(2 * height, height),
+                );
+                RoundedRectangle::new(switch, CornerRadii::new(Size::new(height / 2, height / 2)))
+                    .into_styled(
+                        PrimitiveStyleBuilder::new()
+                            .fill_color(if *on { style.accent } else { style.background })
+                            .stroke_color(foreground)
+                            .stroke_width(2)
+                            .build(),
+                    )
+                    .draw(target)
+                    .ok();
+                let knob = if *on {
+                    switch.top_left + Point::new(height as i32 + 3, 3)
+                } else {
+                    switch.top_left + Point::new(3, 3)
+                };
+                Circle::new(knob, height.saturating_sub(6))
+                    .into_styled(PrimitiveStyle::with_fill(foreground))
+                    .draw(target)
+                    .ok();
+                let label_area = Rectangle::new(
+                    self.area.top_left,
+                    Size::new(
+                        self.area.size.width.saturating_sub(2 * height + 8),
+                        self.area.size.height,
+                    ),
+                );
+                let text = text.align(Alignment::Left, VerticalAlignment::Middle);
+                Label::new(&self.label, label_area, text).draw(target).ok();
+            }
+            WidgetKind::Slider { value, min, max } => {
+                let half = Size::new(self.area.size.width, self.area.size.height / 2);
+                let label = format!("{}: {}", self.label, value);
+                let text = text.align(Alignment::Left, VerticalAlignment::Middle);
+                Label::new(&label, Rectangle::new(self.area.top_left, half), text)
+                    .draw(target)
+                    .ok();
+                let (start, end) = self.slider_track();
+                let x = start.x + (end.x - start.x) * (value - min) / (max - min).max(1);
+                let knob = Point::new(x, start.y);
+                Line::new(start, end)
+                    .into_styled(PrimitiveStyle::with_stroke(foreground, 4))
+                    .draw(target)
+                    .ok();
+                Line::new(start, knob)
+                    .into_styled(PrimitiveStyle::with_stroke(style.accent, 4))
+                    .draw(target)
+                    .ok();
+                Circle::with_center(knob, self.area.size.height / 3)
+                    .into_styled(PrimitiveStyle::with_fill(foreground))
+                    .draw(target)
+                    .ok();
+            }
+            WidgetKind::List {
+                items,
+                item_height,
+                selected,
+                scroll,
+            } => {
+                let visible = self.visible_items();
+                for (row, index) in (*scroll..items.len()).take(visible).enumerate() {
+                    let area = Rectangle::new(
+                        self.area.top_left + Point::new(0, (row as u32 * item_height) as i32),
+                        Size::new(self.area.size.width.saturating_sub(6), *item_height),
+                    );
+                    if *selected == Some(index) {
+                        area.into_styled(PrimitiveStyle::with_fill(style.accent))
+                            .draw(target)
+                            .ok();
+                    }
+                    let text = text.align(Alignment::Left, VerticalAlignment::Middle);
+                    Label::new(&items[index], area.offset(-4), text)
+                        .draw(target)
+                        .ok();
+                }
+                // Scroll bar on the right when the items don't fit
+                if items.len() > visible && visible > 0 {
+                    let height = self.area.size.height;
+                    let bar = Rectangle::new(
+                        self.area.top_left
+                            + Point::new(
+                                self.area.size.width as i32 - 4,
+                                (height as usize * scroll / items.len()) as i32,
+                            ),
+                        Size::new(4, (height as usize * visible / items.len()) as u32),
+                    );
+                    bar.into_styled(PrimitiveStyle::with_fill(foreground))
+                        .draw(target)
+                        .ok();
+                }
+                self.area
+                    .into_styled(PrimitiveStyle::with_stroke(foreground, 1))
+                    .draw(target)
+                    .ok();
+            }
+        }
+    }
+}
+
+impl<C: PixelColor> Ui<C> {
+    pub fn new(area: Rectangle, style: WidgetStyle<C>) -> Self {
+        Self {
+            area,
+            style,
+            widgets: Vec::new(),
+            dialog: None,
+            active: None,
+            dirty: Vec::new(),
+            release_timeout: Duration::from_millis(60),
+        }
+    }
+
+    /// Add a widget, returns its index used in the events
+    pub fn add(&mut self, widget: Widget) -> usize {
+        self.mark_dirty(widget.area);
+        self.widgets.push(widget);
+        self.widgets.len() - 1
+    }
+
+    pub fn widget(&self, index: usize) -> &Widget {
+        &self.widgets[index]
+    }
+
+    /// Change a widget, it gets drawn again
+    pub fn widget_mut(&mut self, index: usize) -> &mut Widget {
+        self.mark_dirty(self.widgets[index].area);
+        &mut self.widgets[index]
+    }
+
+    pub fn open_dialog(&mut self, dialog: Dialog) {
+        let width = (self.area.size.width * 2 / 3).min(420);
+        let message = self
+            .style
+            .text
+            .measure(&dialog.message, width.saturating_sub(24));
+        let title = TextStyle::large(self.style.foreground);
+        let height = title.line_height() + message.height + 2 * 48;
+        let area = Rectangle::with_center(self.area.center(), Size::new(width, height));
+        self.active = None;
+        self.mark_dirty(area);
+        self.dialog = Some((dialog, area));
+    }
+
+    pub fn close_dialog(&mut self) {
+        if let Some((_, area)) = self.dialog.take() {
+            self.mark_dirty(area);
+        }
+    }
+
+    /// Everything changed since the last draw
+    pub fn dirty(&self) -> &[Rectangle] {
+        &self.dirty
+    }
+
+    pub fn mark_dirty(&mut self, area: Rectangle) {
+        if !self.dirty.contains(&area) {
+            self.dirty.push(area);
+        }
+    }
+
+    fn dialog_button_area(&self, area: Rectangle, index: usize, count: usize) -> Rectangle {
+        let gap = 12;
+        let width = area.size.width.saturating_sub(gap * (count as u32 + 1)) / count.max(1) as u32;
+        Rectangle::new(
+            Point::new(
+                area.top_left.x + (gap + index as u32 * (width + gap)) as i32,
+                area.top_left.y + area.size.height as i32 - 48,
+            ),
+            Size::new(width, 36),
+        )
+    }
+
+    fn hit(&self, point: Point) -> Option<Target> {
+        if let Some((dialog, area)) = &self.dialog {
+            // Nothing under the dialog can be touched
+            return (0..dialog.buttons.len())
+                .find(|i| {
+                    self.dialog_button_area(*area, *i, dialog.buttons.len())
+                        .contains(point)
+                })
+                .map(Target::DialogButton);
+        }
+        self.widgets
+            .iter()
+            .position(|widget| widget.enabled && widget.area.contains(point))
+            .map(Target::Widget)
+    }
+
+    /// Feed the touch samples, `None` when there is none. Returns what the touch did, if anything.
+    pub fn on_touch(&mut self, touch: Option<TouchPoint>, now: Instant) -> Option<UiEvent> {
+        let point = touch.map(|touch| Point::new(touch.x as i32, touch.y as i32));
+        match (point, self.active) {
+            (Some(point), None) => self.press(point, now),
+            (Some(point), Some(mut active)) => {
+                active.last = point;
+                active.last_seen = now;
+                self.active = Some(active);
+                self.drag(point)
+            }
+            (None, Some(active)) if now - active.last_seen > self.release_timeout => {
+                self.active = None;
+                self.release(active)
+            }
+            _ => None,
+        }
+    }
+
+    fn press(&mut self, point: Point, now: Instant) -> Option<UiEvent> {
+        let target = self.hit(point)?;
+        let start_scroll = match target {
+            Target::Widget(index) => match self.widgets[index].kind {
+                WidgetKind::List { scroll, .. } => scroll,
+                _ => 0,
+            },
+            Target::DialogButton(_) => 0,
+        };
+        self.active = Some(Active {
+            target,
+            start: point,
+            last: point,
+            last_seen: now,
+            start_scroll,
+            scrolled: false,
+        });
+        let index = match target {
+            Target::Widget(index) => index,
+            Target::DialogButton(_) => {
+                if let Some((_, area)) = self.dialog {
+                    self.mark_dirty(area);
+                }
+                return None;
+            }
+        };
+        let widget = self.widget_mut(index);
+        match &mut widget.kind {
+            WidgetKind::Toggle { on } => {
+                *on = !*on;
+                Some(UiEvent::Toggled(index, *on))
+            }
+            WidgetKind::Slider { .. } => self.drag(point),
+            _ => None,
+        }
+    }
+
+    fn drag(&mut self, point: Point) -> Option<UiEvent> {
+        let mut active = self.active?;
+        let Target::Widget(index) = active.target else {
+            return None;
+        };
+        let widget = &self.widgets[index];
+        let (area, visible, slider_value) = (
+            widget.area,
+            widget.visible_items(),
+            widget.slider_value_at(point.x),
+        );
+        match &mut self.widgets[index].kind {
+            WidgetKind::Slider { value, .. } => {
+                let new_value = slider_value?;
+                if new_value == *value {
+                    return None;
+                }
+                *value = new_value;
+                self.mark_dirty(area);
+                Some(UiEvent::Changed(index, new_value))
+            }
+            WidgetKind::List {
+                items,
+                item_height,
+                scroll,
+                ..
+            } => {
+                let dy = point.y - active.start.y;
+                if !active.scrolled && dy.abs() < SCROLL_THRESHOLD {
+                    return None;
+                }
+                active.scrolled = true;
+                let rows = -dy / *item_height as i32;
+                let last = items.len().saturating_sub(visible) as i32;
+                let new_scroll = (active.start_scroll as i32 + rows).clamp(0, last) as usize;
+                let changed = new_scroll != *scroll;
+                *scroll = new_scroll;
+                self.active = Some(active);
+                if changed {
+                    self.mark_dirty(area);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn release(&mut self, active: Active) -> Option<UiEvent> {
+        match active.target {
+            Target::DialogButton(button) => {
+                let (dialog, area) = self.dialog.as_ref()?;
+                let inside = self
+                    .dialog_button_area(*area, button, dialog.buttons.len())
+                    .contains(active.last);
+                if inside {
+                    self.close_dialog();
+                    return Some(UiEvent::DialogClosed(button));
+                }
+                let area = *area;
+                self.mark_dirty(area);
+                None
+            }
+            Target::Widget(index) => {
+                let widget = &self.widgets[index];
+                let area = widget.area;
+                let inside = area.contains(active.last);
+                let item = match &widget.kind {
+                    WidgetKind::List {
+                        items,
+                        item_height,
+                        scroll,
+                        ..
+                    } if !active.scrolled && inside => {
+                        let row = (active.last.y - area.top_left.y) as u32 / item_height;
+                        Some(scroll + row as usize).filter(|item| *item < items.len())
+                    }
+                    _ => None,
+                };
+                self.mark_dirty(area);
+                match &mut self.widgets[index].kind {
+                    WidgetKind::Button if inside => Some(UiEvent::Pressed(index)),
+                    WidgetKind::List { selected, .. } => {
+                        let item = item?;
+                        *selected = Some(item);
+                        Some(UiEvent::Selected(index, item))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn is_pressed(&self, target: Target) -> bool {
+        self.active.is_some_and(|active| active.target == target)
+    }
+
+    /// Draw everything, e.g. when the screen is shown
+    pub fn draw<D>(&mut self, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.dirty.clear();
+        self.mark_dirty(self.area);
+        self.draw_dirty(target);
+    }
+
+    /// Draw the dirty parts of the screen again, returns them so they can be sent to the display
+    pub fn draw_dirty<D>(&mut self, target: &mut D) -> Vec<Rectangle>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let dirty = std::mem::take(&mut self.dirty);
+        for area in &dirty {
+            let cleared = area.intersection(&self.area);
+            cleared
+                .into_styled(PrimitiveStyle::with_fill(self.style.background))
+                .draw(target)
+                .ok();
+            for (index, widget) in self.widgets.iter().enumerate() {
+                if !widget.area.intersection(&cleared).is_zero_sized() {
+                    let pressed = self.is_pressed(Target::Widget(index));
+                    widget.draw(&self.style, pressed, target);
+                }
+            }
+        }
+        if let Some((dialog, area)) = &self.dialog {
+            if dirty
+                .iter()
+                .any(|dirty| !dirty.intersection(area).is_zero_sized())
+            {
+                self.draw_dialog(dialog, *area, target);
+            }
+        }
+        dirty
+    }
+
+    fn draw_dialog<D>(&self, dialog: &Dialog, area: Rectangle, target: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let style = &self.style;
+        area.into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(style.background)
+                .stroke_color(style.foreground)
+                .stroke_width(2)
+                .build(),
+        )
+        .draw(target)
+        .ok();
+        let inner = area.offset(-12);
+        let title =
+            TextStyle::large(style.foreground).align(Alignment::Center, VerticalAlignment::Top);
+        Label::new(&dialog.title, inner, title).draw(target).ok();
+        let message_area = Rectangle::new(
+            inner.top_left + Point::new(0, title.line_height() as i32 + 8),
+            Size::new(
+                inner.size.width,
+                inner.size.height.saturating_sub(title.line_height() + 56),
+            ),
+        );
+        let message = style.text.align(Alignment::Center, VerticalAlignment::Top);
+        Label::new(&dialog.message, message_area, message)
+            .draw(target)
+            .ok();
+        for (i, label) in dialog.buttons.iter().enumerate() {
+            let button = Widget::button(
+                label,
+                self.dialog_button_area(area, i, dialog.buttons.len()),
+            );
+            button.draw(style, self.is_pressed(Target::DialogButton(i)), target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::RgbColor;
+
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    fn ui() -> Ui<Rgb565> {
+        let style = WidgetStyle {
+            background: Rgb565::BLACK,
+            foreground: Rgb565::WHITE,
+            accent: Rgb565::GREEN,
+            text: TextStyle::medium(Rgb565::WHITE),
+        };
+        Ui::new(Rectangle::new(Point::zero(), Size::new(800, 480)), style)
+    }
+
+    fn touch(x: i32, y: i32) -> Option<TouchPoint> {
+        Some(TouchPoint {
+            id: 0,
+            x: x as u16,
+            y: y as u16,
+            size: 10,
+        })
+    }
+
+    /// Touch at each point 10ms apart, then lift the finger, returns all the events
+    fn swipe(ui: &mut Ui<Rgb565>, points: &[(i32, i32)], now: &mut Instant) -> Vec<UiEvent> {
+        let mut events = Vec::new();
+        for (x, y) in points {
+            events.extend(ui.on_touch(touch(*x, *y), *now));
+            *now += Duration::from_millis(10);
+        }
+        // Still down until the release timeout is over
+        events.extend(ui.on_touch(None, *now));
+        *now += Duration::from_millis(100);
+        events.extend(ui.on_touch(None, *now));
+        assert!(ui.active.is_none());
+        events
+    }
+
+    fn tap(ui: &mut Ui<Rgb565>, x: i32, y: i32, now: &mut Instant) -> Vec<UiEvent> {
+        swipe(ui, &[(x, y)], now)
+    }
+
+    #[test]
+    fn button_pressed_when_released_inside() {
+        let mut ui = ui();
+        let button = ui.add(Widget::button(
+            "OK",
+            Rectangle::new(Point::new(100, 100), Size::new(100, 50)),
+        ));
+        let mut now = Instant::now();
+        assert_eq!(tap(&mut ui, 150, 120, &mut now), [UiEvent::Pressed(button)]);
+        // Sliding off the button cancels it
+        assert!(swipe(&mut ui, &[(150, 120), (300, 300)], &mut now).is_empty());
+        assert!(tap(&mut ui, 300, 300, &mut now).is_empty());
+    }
+
+    #[test]
+    fn disabled_widgets_ignore_touches() {
+        let mut ui = ui();
+        let button = ui.add(Widget::button(
+            "OK",
+            Rectangle::new(Point::new(100, 100), Size::new(100, 50)),
+        ));
+        ui.widget_mut(button).enabled = false;
+        assert!(tap(&mut ui, 150, 120, &mut Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn toggle_switches_on_press() {
+        let mut ui = ui();
+        let toggle = ui.add(Widget::toggle(
+            "Sound",
+            Rectangle::new(Point::new(100, 100), Size::new(200, 40)),
+            false,
+        ));
+        let mut now = Instant::now();
+        assert_eq!(
+            tap(&mut ui, 150, 120, &mut now),
+            [UiEvent::Toggled(toggle, true)]
+        );
+        assert_eq!(
+            tap(&mut ui, 150, 120, &mut now),
+            [UiEvent::Toggled(toggle, false)]
+        );
+        assert_eq!(ui.widget(toggle).kind, WidgetKind::Toggle { on: false });
+    }
+
+    #[test]
+    fn slider_follows_the_finger() {
+        let mut ui = ui();
+        let slider = ui.add(Widget::slider(
+            "Level",
+            Rectangle::new(Point::new(100, 100), Size::new(240, 40)),
+            0,
+            10,
+            5,
+        ));
+        let (start, end) = ui.widget(slider).slider_track();
+        let events = swipe(
+            &mut ui,
+            &[
+                (start.x, 130),
+                (end.x, 130),
+                (end.x + 50, 130),
+                (start.x - 50, 130),
+            ],
+            &mut Instant::now(),
+        );
+        assert_eq!(
+            events,
+            [
+                UiEvent::Changed(slider, 0),
+                UiEvent::Changed(slider, 10),
+                UiEvent::Changed(slider, 0),
+            ]
+        );
+    }
+
+    fn list(ui: &mut Ui<Rgb565>) -> usize {
+        let items = (0..10).map(|i| format!("Item {}", i)).collect();
+        // 4 rows of 40 pixels shown
+        ui.add(Widget::list(
+            Rectangle::new(Point::new(100, 100), Size::new(200, 160)),
+            items,
+            40,
+        ))
+    }
+
+    #[test]
+    fn list_selects_the_tapped_item() {
+        let mut ui = ui();
+        let list = list(&mut ui);
+        let mut now = Instant::now();
+        // A few pixels of movement is still a tap
+        let events = swipe(&mut ui, &[(150, 190), (152, 194)], &mut now);
+        assert_eq!(events, [UiEvent::Selected(list, 2)]);
+        let WidgetKind::List { selected, .. } = ui.widget(list).kind else {
+            unreachable!();
+        };
+        assert_eq!(selected, Some(2));
+    }
+
+    #[test]
+    fn list_scrolls_instead_of_selecting() {
+        let mut ui = ui();
+        let list = list(&mut ui);
+        let mut now = Instant::now();
+        // Two rows up
+        let events = swipe(&mut ui, &[(150, 250), (150, 200), (150, 170)], &mut now);
+        assert!(events.is_empty());
+        let scroll = |ui: &Ui<Rgb565>| match ui.widget(list).kind {
+            WidgetKind::List { scroll, .. } => scroll,
+            _ => unreachable!(),
+        };
+        assert_eq!(scroll(&ui), 2);
+        // The tapped row is counted from the first one shown
+        assert_eq!(
+            tap(&mut ui, 150, 110, &mut now),
+            [UiEvent::Selected(list, 2)]
+        );
+        // Never past the last items
+        swipe(&mut ui, &[(150, 255), (150, 0)], &mut now);
+        assert_eq!(scroll(&ui), 6);
+        swipe(&mut ui, &[(150, 105), (150, 470)], &mut now);
+        assert_eq!(scroll(&ui), 0);
+    }
+
+    #[test]
+    fn dialog_takes_all_the_touches() {
+        let mut ui = ui();
+        let button = ui.add(Widget::button(
+            "OK",
+            Rectangle::new(Point::new(0, 0), Size::new(800, 480)),
+        ));
+        ui.open_dialog(Dialog {
+            title: "Sure?".to_string(),
+            message: "Really".to_string(),
+            buttons: vec!["Yes".to_string(), "No".to_string()],
+        });
+        let area = ui.dialog.as_ref().unwrap().1;
+        let mut now = Instant::now();
+        // Neither outside nor inside the dialog does the button under it get pressed
+        assert!(tap(&mut ui, 10, 10, &mut now).is_empty());
+        assert!(tap(&mut ui, area.center().x, area.center().y, &mut now).is_empty());
+        let no = ui.dialog_button_area(area, 1, 2).center();
+        assert_eq!(
+            tap(&mut ui, no.x, no.y, &mut now),
+            [UiEvent::DialogClosed(1)]
+        );
+        assert!(ui.dialog.is_none());
+        assert_eq!(tap(&mut ui, 10, 10, &mut now), [UiEvent::Pressed(button)]);
+    }
+
+    #[test]
+    fn only_the_changed_widgets_are_dirty() {
+        let mut ui = ui();
+        let first = Rectangle::new(Point::new(100, 100), Size::new(100, 50));
+        let second = Rectangle::new(Point::new(300, 100), Size::new(100, 50));
+        ui.add(Widget::button("A", first));
+        ui.add(Widget::toggle("B", second, false));
+        let mut display = FrameBuffer::new(800, 480);
+        ui.draw(&mut display);
+        assert!(ui.dirty().is_empty());
+        assert_ne!(display.get_pixel(100 + 50, 100), Rgb565::BLACK);
+
+        let mut now = Instant::now();
+        ui.on_touch(touch(350, 120), now);
+        assert_eq!(ui.dirty(), [second]);
+        assert_eq!(ui.draw_dirty(&mut display), [second]);
+        assert!(ui.draw_dirty(&mut display).is_empty());
+
+        now += Duration::from_millis(100);
+        ui.on_touch(None, now);
+        assert_eq!(ui.draw_dirty(&mut display), [second]);
+    }
+
+    #[test]
+    fn closing_the_dialog_draws_the_widgets_under_it() {
+        let mut ui = ui();
+        ui.add(Widget::button(
+            "OK",
+            Rectangle::new(Point::new(0, 0), Size::new(800, 480)),
+        ));
+        let mut display = FrameBuffer::new(800, 480);
+        ui.draw(&mut display);
+        ui.open_dialog(Dialog {
+            title: "Sure?".to_string(),
+            message: "Really".to_string(),
+            buttons: vec!["OK".to_string()],
+        });
+        let area = ui.dialog.as_ref().unwrap().1;
+        assert_eq!(ui.draw_dirty(&mut display), [area]);
+        ui.close_dialog();
+        assert_eq!(ui.draw_dirty(&mut display), [area]);
+        // The button background is back where the dialog border was
+        assert_eq!(
+            display.get_pixel(area.top_left.x as usize, area.center().y as usize),
+            Rgb565::BLACK
+        );
+    }
+}